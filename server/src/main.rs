@@ -33,6 +33,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create a thread pool to handle incoming TcpStreams (i.e., connections from clients)
     let pool: thread::Pool = thread::Pool::new(4); // When idle, threads seem to consume, on average, ~40 kB of memory each
 
+    // The configuration is shared (read-only) by every thread in the pool
+    let config: std::sync::Arc<tcp::ServerConfig> = std::sync::Arc::new(tcp::ServerConfig::from_env());
+
     // Handle the TcpStream (connection) of each client who connects to the server (via the TcpListener)
     for tcp_stream in tcp_listener.incoming() {
         match tcp_stream {
@@ -46,7 +49,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
 
                 // Handle the TcpStream (connection) using a thread from the thread pool
-                pool.execute(/* move? */ || tcp::handle_tcp_stream(tcp_stream));
+                let config: std::sync::Arc<tcp::ServerConfig> = std::sync::Arc::clone(&config);
+                pool.execute(move || tcp::handle_tcp_stream(tcp_stream, config));
             }
             Err(e) => {
                 println!("ERROR (MAIN): TcpStream Error: {}", e)
@@ -0,0 +1,117 @@
+// tcp/config.rs
+
+// Decides whether a request that sent "Expect: 100-continue" may go on to send its message body.
+// Returning None accepts the body (the client is sent 100 Continue); returning Some(HttpResponse) rejects it with that final response instead.
+pub type ExpectContinueHook = fn(&super::http::HttpRequest) -> Option<super::http::HttpResponse>;
+
+// The default ExpectContinueHook, which accepts every body that is within parser_limits.max_body_size
+fn accept_expect_continue(_http_request: &super::http::HttpRequest) -> Option<super::http::HttpResponse> {
+    None
+}
+
+// Settings that control how each TcpStream (i.e., connection) is handled. One ServerConfig is shared by every thread in the pool.
+pub struct ServerConfig {
+    pub site_root: std::path::PathBuf, // The directory the pages are served from. No request can reach a file outside of it.
+    pub symlink_policy: super::resolver::SymlinkPolicy,
+    pub serve_hidden_files: bool,      // Serve hidden and backup files (e.g., ".git", "index.html~") instead of blocking them
+    pub blocked_path_policy: super::resolver::BlockedPathPolicy, // Whether a blocked path gets a 403 or a 404
+    pub index_files: Vec<String>,      // The files (in order of preference) that are served for a request for a directory
+    pub directory_listings: Vec<String>, // Request paths (e.g., "/artefacts/") whose directories without an index file get a generated listing
+    pub scheme: String,     // The scheme clients use to reach the listener, used to build each request's effective request URI
+    pub parser_limits: super::http::ParserLimits, // Requests over any of these limits are rejected with 414, 431 or 413
+    pub idle_timeout: std::time::Duration,   // How long a persistent connection may sit without a new request before it is closed
+    pub max_requests_per_connection: usize,  // The response to the last allowed request is sent with "Connection: close"
+    pub expect_continue_hook: ExpectContinueHook,
+    pub error_pages: std::collections::HashMap<u16, std::path::PathBuf>, // Custom error page for a status code, used instead of site/{status code}.html
+    pub mime_table: super::mime::MimeTable, // The Content-Type of each file served, by its extension
+    pub compression: bool,                  // Compress text bodies with gzip or deflate for the clients that accept it
+    pub nosniff: bool,                      // Send "X-Content-Type-Options: nosniff" so browsers trust the Content-Type instead of sniffing the content
+}
+
+impl Default for ServerConfig {
+    fn default() -> ServerConfig {
+        ServerConfig {
+            site_root: std::path::PathBuf::from("site"),
+            symlink_policy: super::resolver::SymlinkPolicy::WithinRoot,
+            serve_hidden_files: false,
+            blocked_path_policy: super::resolver::BlockedPathPolicy::NotFound,
+            index_files: vec![String::from("index.html"), String::from("index.htm")],
+            directory_listings: Vec::new(),
+            scheme: String::from("http"),
+            parser_limits: super::http::ParserLimits::default(),
+            idle_timeout: std::time::Duration::new(5, 0),
+            max_requests_per_connection: 100,
+            expect_continue_hook: accept_expect_continue,
+            error_pages: std::collections::HashMap::new(),
+            mime_table: super::mime::MimeTable::default(),
+            compression: true,
+            nosniff: false,
+        }
+    }
+}
+
+impl ServerConfig {
+    // The default ServerConfig, with any of the settings below overridden by environment variables so that each deployment can tune them
+    // without a rebuild (e.g., SERVER_MAX_BODY_SIZE=1048576). Values that can't be parsed are ignored with a warning.
+    pub fn from_env() -> ServerConfig {
+        let mut config: ServerConfig = ServerConfig::default();
+        let parser_limits: &mut super::http::ParserLimits = &mut config.parser_limits;
+        read_env_var("SERVER_MAX_REQUEST_LINE_LENGTH", &mut parser_limits.max_request_line_length);
+        read_env_var("SERVER_MAX_HEADER_COUNT", &mut parser_limits.max_header_count);
+        read_env_var("SERVER_MAX_FIELD_LINE_LENGTH", &mut parser_limits.max_field_line_length);
+        read_env_var("SERVER_MAX_HEADER_SECTION_SIZE", &mut parser_limits.max_header_section_size);
+        read_env_var("SERVER_MAX_BODY_SIZE", &mut parser_limits.max_body_size);
+        read_env_var("SERVER_MAX_REQUESTS_PER_CONNECTION", &mut config.max_requests_per_connection);
+        // A read timeout of zero isn't a timeout at all (std::net::TcpStream::set_read_timeout rejects it), so it is ignored as well
+        let mut idle_timeout_secs: u64 = config.idle_timeout.as_secs();
+        read_env_var("SERVER_IDLE_TIMEOUT_SECS", &mut idle_timeout_secs);
+        match idle_timeout_secs {
+            0 => println!("WARNING (SERVER_CONFIG): Ignoring SERVER_IDLE_TIMEOUT_SECS=0, the idle timeout has to be at least a second"),
+            _ => config.idle_timeout = std::time::Duration::from_secs(idle_timeout_secs),
+        }
+        read_env_var("SERVER_COMPRESSION", &mut config.compression);
+        read_env_var("SERVER_NOSNIFF", &mut config.nosniff);
+        read_env_var("SERVER_SITE_ROOT", &mut config.site_root);
+        read_env_var("SERVER_SYMLINK_POLICY", &mut config.symlink_policy); // deny, within-root or follow
+        read_env_var("SERVER_SERVE_HIDDEN_FILES", &mut config.serve_hidden_files);
+        read_env_var("SERVER_BLOCKED_PATH_STATUS", &mut config.blocked_path_policy); // 403 or 404
+
+        // Comma-separated lists (e.g., SERVER_INDEX_FILES=index.html,default.htm and SERVER_DIRECTORY_LISTINGS=/artefacts/,/downloads/)
+        read_env_list("SERVER_INDEX_FILES", &mut config.index_files);
+        read_env_list("SERVER_DIRECTORY_LISTINGS", &mut config.directory_listings);
+        // A listing is enabled for a directory, so its path has to end in "/" (e.g., "/artefacts" is read as "/artefacts/", not as a prefix of "/artefacts-old/")
+        for listed_path in &mut config.directory_listings {
+            if !listed_path.ends_with('/') { listed_path.push('/') }
+        }
+
+        // Custom error pages, one per status code (e.g., SERVER_ERROR_PAGE_404=/srv/pages/not-found.html)
+        // and custom media types, one per file extension (e.g., SERVER_MIME_TYPE_md=text/markdown)
+        for (name, value) in std::env::vars() {
+            if let Some(extension) = name.strip_prefix("SERVER_MIME_TYPE_") {
+                config.mime_table.insert(extension, &value);
+                continue;
+            }
+            let Some(status_code) = name.strip_prefix("SERVER_ERROR_PAGE_") else { continue };
+            match status_code.parse::<u16>() {
+                Ok(status_code) if (400..=599).contains(&status_code) => { config.error_pages.insert(status_code, std::path::PathBuf::from(value)); }
+                _ => println!("WARNING (SERVER_CONFIG): Ignoring {}, {:?} is not an error status code", name, status_code),
+            }
+        }
+        config
+    }
+}
+
+// Overwrites values with the comma-separated items of the environment variable, if it is set
+fn read_env_list(name: &str, values: &mut Vec<String>) {
+    let Ok(raw_value) = std::env::var(name) else { return };
+    *values = raw_value.split(',').map(|value| value.trim()).filter(|value| !value.is_empty()).map(String::from).collect();
+}
+
+// Overwrites value with the parsed contents of the environment variable, if it is set
+fn read_env_var<T: std::str::FromStr>(name: &str, value: &mut T) {
+    let Ok(raw_value) = std::env::var(name) else { return };
+    match raw_value.trim().parse::<T>() {
+        Ok(parsed_value) => *value = parsed_value,
+        Err(_) => println!("WARNING (SERVER_CONFIG): Ignoring {}={:?}, it is not a valid value", name, raw_value),
+    }
+}
@@ -0,0 +1,63 @@
+// tcp/error.rs
+
+// Everything that can end the handling of a TcpStream (i.e., connection) early.
+// None of these are fatal to the server: each one turns into an error response and/or a clean close of that one connection.
+#[derive(Debug)]
+pub enum ServerError {
+    Io(std::io::Error),                   // Reading from or writing to the TcpStream failed -> close the connection
+    Parse(super::http::HttpRequestError), // The client sent something we can't (or won't) handle -> send an error response, then close
+    Timeout,                              // The client went quiet for longer than the read timeout -> close the connection
+}
+
+impl std::fmt::Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ServerError::Io(e) => write!(f, "I/O error: {}", e),
+            ServerError::Parse(e) => write!(f, "invalid HTTP request: {:?}", e),
+            ServerError::Timeout => write!(f, "timed out waiting for the client"),
+        }
+    }
+}
+
+// A read timeout shows up as WouldBlock (Unix) or TimedOut (Windows) - see std::net::TcpStream::set_read_timeout
+impl From<std::io::Error> for ServerError {
+    fn from(e: std::io::Error) -> ServerError {
+        match e.kind() {
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut => ServerError::Timeout,
+            _ => ServerError::Io(e),
+        }
+    }
+}
+
+impl From<super::http::HttpRequestError> for ServerError {
+    fn from(e: super::http::HttpRequestError) -> ServerError {
+        ServerError::Parse(e)
+    }
+}
+
+impl From<super::reader::StreamReadError> for ServerError {
+    fn from(e: super::reader::StreamReadError) -> ServerError {
+        match e {
+            super::reader::StreamReadError::Io(e) => ServerError::from(e),
+            super::reader::StreamReadError::UnexpectedEof => ServerError::Io(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)),
+            super::reader::StreamReadError::RequestLineTooLong => ServerError::Parse(super::http::HttpRequestError::UriTooLong),
+            super::reader::StreamReadError::HeaderSectionTooLarge => ServerError::Parse(super::http::HttpRequestError::HeaderFieldsTooLarge),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_server_error_from() {
+        assert!(matches!(ServerError::from(std::io::Error::from(std::io::ErrorKind::WouldBlock)), ServerError::Timeout));
+        assert!(matches!(ServerError::from(std::io::Error::from(std::io::ErrorKind::TimedOut)), ServerError::Timeout));
+        assert!(matches!(ServerError::from(std::io::Error::from(std::io::ErrorKind::ConnectionReset)), ServerError::Io(_)));
+        assert!(matches!(
+            ServerError::from(super::super::reader::StreamReadError::RequestLineTooLong),
+            ServerError::Parse(super::super::http::HttpRequestError::UriTooLong)
+        ));
+    }
+}
@@ -0,0 +1,56 @@
+// tcp/error_page.rs
+
+// Builds the response for an error status (4xx or 5xx), with an HTML page describing it as the message body.
+// The page is the first one of these that can be read:
+//   1. the file configured for the status code in ServerConfig::error_pages
+//   2. "{status code}.html" in the site root (e.g., site/404.html)
+//   3. a minimal page generated from the status code and its reason phrase
+pub fn error_response(status_code: super::http::StatusCode, config: &super::ServerConfig) -> super::http::HttpResponse {
+    let mut http_response: super::http::HttpResponse = super::http::construct_http_response(status_code);
+    http_response.header_field_lines.insert(b"Content-Type", b"text/html; charset=utf-8");
+    super::insert_nosniff_header(&mut http_response, config);
+    http_response.body = Some(super::http::HttpResponseBody::Bytes(error_page(status_code, config)));
+    http_response
+}
+
+fn error_page(status_code: super::http::StatusCode, config: &super::ServerConfig) -> Vec<u8> {
+    if let Some(path) = config.error_pages.get(&status_code.0) {
+        match std::fs::read(path) {
+            Ok(page) => return page,
+            Err(e) => println!("WARNING (ERROR_PAGE): Failed to read the configured error page {}: {}", path.display(), e),
+        }
+    }
+    if let Ok(page) = std::fs::read(config.site_root.join(format!("{}.html", status_code))) { return page }
+    generated_error_page(status_code).into_bytes()
+}
+
+fn generated_error_page(status_code: super::http::StatusCode) -> String {
+    let title: String = format!("{} {}", status_code, status_code.reason_phrase()).trim_end().to_string(); // unregistered codes have no reason phrase
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en-US\">\n<head>\n  <meta charset=\"utf-8\">\n  <title>{0}</title>\n</head>\n<body>\n  <h1>{0}</h1>\n</body>\n</html>\n",
+        title
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{http, ServerConfig};
+
+    #[test]
+    fn test_error_response() {
+        let config: ServerConfig = ServerConfig::default();
+
+        // No configured page and no site/599.html, so the page is generated
+        let http_response: http::HttpResponse = error_response(http::StatusCode(599), &config);
+        assert_eq!(http_response.start_line.status_code, http::StatusCode(599));
+        assert_eq!(http_response.header_field_lines.get(b"Content-Type"), Some(&b"text/html; charset=utf-8"[..]));
+        let page: Vec<u8> = match http_response.body {
+            Some(http::HttpResponseBody::Bytes(page)) => page,
+            body => panic!("expected an in-memory page, got {:?}", body),
+        };
+        assert!(String::from_utf8(page).unwrap().contains("<h1>599</h1>"));
+
+        assert!(generated_error_page(http::StatusCode::NOT_FOUND).contains("<title>404 Not Found</title>"));
+    }
+}
@@ -0,0 +1,173 @@
+// tcp/http/body.rs
+
+// How much of a streamed body is held in memory at once while it is being written
+const WRITE_BUFFER_SIZE: usize = 16 * 1024;
+
+// Produces a message body piece by piece, for content that is generated on the fly (e.g., a directory listing)
+pub trait ChunkGenerator {
+    // Returns the next piece of the body, or None once the body is complete
+    fn next_chunk(&mut self) -> std::io::Result<Option<Vec<u8>>>;
+
+    // Called once the body is complete, for fields that can only be known afterwards (e.g., a checksum of the body).
+    // They are sent in the trailer section when the body is chunked, and dropped otherwise - rfc9110#section-6.5
+    fn trailer_fields(&mut self) -> super::HeaderMap {
+        super::HeaderMap::new()
+    }
+}
+
+/// The message body of an HttpResponse.
+///
+/// Only `Bytes` is held in memory as a whole. The other kinds are written to the connection as they are read (or generated),
+/// a buffer of at most WRITE_BUFFER_SIZE bytes at a time, so serving a 2 GB file takes as much memory as serving a 2 KB one.
+pub enum HttpResponseBody {
+    Bytes(Vec<u8>),
+    File { file: std::fs::File, length: u64 },
+    Reader { reader: Box<dyn std::io::Read + Send>, length: Option<u64> },
+    Chunks(Box<dyn ChunkGenerator + Send>),
+    Encoded { body: Box<HttpResponseBody>, content_coding: super::ContentCoding }, // compressed as it is written, see content_coding::compress_response
+}
+
+impl HttpResponseBody {
+    // Opens the file at the given path as a body, failing if it can't be opened or isn't a regular file (e.g., a directory)
+    pub fn from_path(path: &std::path::Path) -> std::io::Result<HttpResponseBody> {
+        let file: std::fs::File = std::fs::File::open(path)?;
+        let metadata: std::fs::Metadata = file.metadata()?;
+        if !metadata.is_file() { return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "not a regular file")) }
+        Ok(HttpResponseBody::File { file, length: metadata.len() })
+    }
+
+    // The length of the body in octets, if it is known before the body is written
+    pub fn length(&self) -> Option<u64> {
+        match self {
+            HttpResponseBody::Bytes(bytes) => Some(bytes.len() as u64),
+            HttpResponseBody::File { length, .. } => Some(*length),
+            HttpResponseBody::Reader { length, .. } => *length,
+            HttpResponseBody::Chunks(_) => None,
+            HttpResponseBody::Encoded { .. } => None, // only known once the body has been compressed
+        }
+    }
+
+    // Writes the whole body to the writer as is (i.e., framed by Content-Length or by closing the connection)
+    pub fn write_to<W: std::io::Write>(self, writer: &mut W) -> std::io::Result<()> {
+        self.write_body(writer)?;
+        Ok(())
+    }
+
+    // Writes the whole body to the writer with the chunked transfer coding applied, followed by any trailer fields - rfc9112#section-7.1
+    pub fn write_chunked_to<W: std::io::Write>(self, writer: &mut W) -> std::io::Result<()> {
+        let mut chunked_encoder: super::ChunkedEncoder<&mut W> = super::ChunkedEncoder::new(writer);
+        let trailer_fields: super::HeaderMap = self.write_body(&mut chunked_encoder)?;
+        chunked_encoder.finish(&trailer_fields)?;
+        Ok(())
+    }
+
+    // Writes the body, returning its trailer fields. A body of known length that turns out to be shorter is an UnexpectedEof error,
+    // since the Content-Length that was already sent can no longer be honoured (so the connection has to be closed).
+    // (The writer is a trait object, so that an Encoded body can write the body it wraps through a ContentEncoder.)
+    fn write_body(self, writer: &mut dyn std::io::Write) -> std::io::Result<super::HeaderMap> {
+        match self {
+            HttpResponseBody::Bytes(bytes) => writer.write_all(&bytes)?,
+            HttpResponseBody::File { mut file, length } => copy_body(&mut file, writer, Some(length))?,
+            HttpResponseBody::Reader { mut reader, length } => copy_body(&mut reader, writer, length)?,
+            HttpResponseBody::Chunks(mut chunk_generator) => {
+                while let Some(chunk) = chunk_generator.next_chunk()? {
+                    writer.write_all(&chunk)?;
+                }
+                return Ok(chunk_generator.trailer_fields());
+            }
+            HttpResponseBody::Encoded { body, content_coding } => {
+                let mut content_encoder: super::ContentEncoder<&mut dyn std::io::Write> = super::ContentEncoder::new(writer, content_coding)?;
+                let trailer_fields: super::HeaderMap = body.write_body(&mut content_encoder)?;
+                content_encoder.finish()?;
+                return Ok(trailer_fields);
+            }
+        }
+        Ok(super::HeaderMap::new())
+    }
+}
+
+impl std::fmt::Debug for HttpResponseBody {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpResponseBody::Bytes(bytes) => write!(f, "Bytes({} bytes)", bytes.len()),
+            HttpResponseBody::File { length, .. } => write!(f, "File({} bytes)", length),
+            HttpResponseBody::Reader { length, .. } => write!(f, "Reader({:?} bytes)", length),
+            HttpResponseBody::Chunks(_) => write!(f, "Chunks"),
+            HttpResponseBody::Encoded { body, content_coding } => write!(f, "Encoded({}, {:?})", content_coding, body),
+        }
+    }
+}
+
+// Copies from the reader to the writer through a fixed size buffer, stopping after length bytes (if given) or at the end of the reader
+fn copy_body<R: std::io::Read + ?Sized, W: std::io::Write + ?Sized>(reader: &mut R, writer: &mut W, length: Option<u64>) -> std::io::Result<()> {
+    let mut buffer: Vec<u8> = vec![0; WRITE_BUFFER_SIZE];
+    let mut remaining: u64 = length.unwrap_or(u64::MAX);
+    while remaining > 0 {
+        let to_read: usize = std::cmp::min(remaining, WRITE_BUFFER_SIZE as u64) as usize;
+        let bytes_read: usize = match reader.read(&mut buffer[..to_read]) {
+            Ok(0) if length.is_none() => return Ok(()),
+            Ok(0) => return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "the body ended before its Content-Length")),
+            Ok(bytes_read) => bytes_read,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buffer[..bytes_read])?;
+        remaining -= bytes_read as u64;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Countdown { from: u8 }
+
+    impl ChunkGenerator for Countdown {
+        fn next_chunk(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+            if self.from == 0 { return Ok(None) }
+            self.from -= 1;
+            Ok(Some(format!("{} ", self.from + 1).into_bytes()))
+        }
+
+        fn trailer_fields(&mut self) -> super::super::HeaderMap {
+            let mut trailer_fields: super::super::HeaderMap = super::super::HeaderMap::new();
+            trailer_fields.append(b"X-Liftoff", b"yes");
+            trailer_fields
+        }
+    }
+
+    #[test]
+    fn test_write_to() {
+        let large: Vec<u8> = (0..WRITE_BUFFER_SIZE * 3 + 7).map(|i| i as u8).collect();
+        let mut written: Vec<u8> = Vec::new();
+        HttpResponseBody::Reader { reader: Box::new(std::io::Cursor::new(large.clone())), length: Some(large.len() as u64) }.write_to(&mut written).unwrap();
+        assert_eq!(written, large);
+
+        let mut written: Vec<u8> = Vec::new();
+        HttpResponseBody::Reader { reader: Box::new(std::io::Cursor::new(large.clone())), length: Some(10) }.write_to(&mut written).unwrap();
+        assert_eq!(written, large[..10].to_vec()); // Never more than the announced length
+
+        let mut written: Vec<u8> = Vec::new();
+        let result: std::io::Result<()> = HttpResponseBody::Reader { reader: Box::new(&b"short"[..]), length: Some(10) }.write_to(&mut written);
+        assert_eq!(result.unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);
+
+        let mut written: Vec<u8> = Vec::new();
+        let body: HttpResponseBody = HttpResponseBody::Chunks(Box::new(Countdown { from: 3 }));
+        assert_eq!(body.length(), None);
+        body.write_to(&mut written).unwrap();
+        assert_eq!(written, b"3 2 1 ".to_vec()); // Trailer fields can't be sent without the chunked transfer coding
+
+        let mut written: Vec<u8> = Vec::new();
+        HttpResponseBody::Chunks(Box::new(Countdown { from: 2 })).write_chunked_to(&mut written).unwrap();
+        assert_eq!(written, b"2\r\n2 \r\n2\r\n1 \r\n0\r\nX-Liftoff: yes\r\n\r\n".to_vec());
+
+        // An encoded body is compressed as it is written, and keeps the trailer fields of the body it wraps
+        let mut written: Vec<u8> = Vec::new();
+        let body: HttpResponseBody = HttpResponseBody::Encoded { body: Box::new(HttpResponseBody::Chunks(Box::new(Countdown { from: 3 }))), content_coding: super::super::ContentCoding::Gzip };
+        assert_eq!(body.length(), None);
+        body.write_chunked_to(&mut written).unwrap();
+        assert!(written.starts_with(b"A\r\n\x1F\x8B")); // The gzip header is the first chunk
+        assert!(written.ends_with(b"\r\n0\r\nX-Liftoff: yes\r\n\r\n"));
+    }
+}
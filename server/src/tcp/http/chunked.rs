@@ -0,0 +1,219 @@
+// tcp/http/chunked.rs
+
+// Chunked Transfer Coding - rfc9112#section-7.1
+//
+//   chunked-body   = *chunk
+//                    last-chunk
+//                    trailer-section
+//                    CRLF
+//
+//   chunk          = chunk-size [ chunk-ext ] CRLF
+//                    chunk-data CRLF
+//   chunk-size     = 1*HEXDIG
+//   last-chunk     = 1*("0") [ chunk-ext ] CRLF
+
+enum ChunkedDecoderState {
+    ChunkSize,           // expecting a chunk-size line
+    ChunkData(u64),      // in the middle of chunk-data, with this many octets left
+    ChunkDataCrlf,       // expecting the CRLF that ends a chunk
+    TrailerSection,      // the last-chunk has been read, expecting zero or more trailer field lines
+    Done,
+}
+
+/// A streaming decoder for a message body sent with `Transfer-Encoding: chunked`.
+///
+/// Reading from a ChunkedDecoder yields the decoded chunk-data and returns `Ok(0)` once the
+/// last-chunk and the trailer section have been consumed. Malformed framing is reported as an
+/// `std::io::ErrorKind::InvalidData` error. The trailer fields are available once decoding is done.
+pub struct ChunkedDecoder<R: std::io::BufRead> {
+    reader: R,
+    state: ChunkedDecoderState,
+    max_section_size: usize, // the limit for a single chunk-size line and for the whole trailer section
+    trailer_fields: super::HeaderMap,
+}
+
+impl<R: std::io::BufRead> ChunkedDecoder<R> {
+    pub fn new(reader: R, max_section_size: usize) -> ChunkedDecoder<R> {
+        ChunkedDecoder {
+            reader,
+            state: ChunkedDecoderState::ChunkSize,
+            max_section_size,
+            trailer_fields: super::HeaderMap::new(),
+        }
+    }
+
+    // Consumes the decoder, returning the trailer fields that followed the last-chunk
+    pub fn into_trailer_fields(self) -> super::HeaderMap {
+        self.trailer_fields
+    }
+
+    // Reads a single CRLF terminated line (of at most `limit` octets, CRLF included) and returns it without the CRLF
+    fn read_line(&mut self, limit: usize) -> std::io::Result<Vec<u8>> {
+        let mut line: Vec<u8> = Vec::new();
+        std::io::BufRead::read_until(&mut std::io::Read::take(&mut self.reader, limit as u64), b'\n', &mut line)?;
+        if line.ends_with(b"\r\n") {
+            line.truncate(line.len() - 2);
+            return Ok(line);
+        }
+        if line.len() == limit || line.ends_with(b"\n") { return Err(invalid_data("chunked line is too long or not CRLF terminated")) }
+        Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "connection closed in the middle of a chunked message body"))
+    }
+}
+
+impl<R: std::io::BufRead> std::io::Read for ChunkedDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        loop {
+            match self.state {
+                ChunkedDecoderState::ChunkSize => {
+                    let line: Vec<u8> = self.read_line(self.max_section_size)?;
+                    let chunk_size: u64 = parse_chunk_size(&line)?;
+                    self.state = if chunk_size == 0 { ChunkedDecoderState::TrailerSection } else { ChunkedDecoderState::ChunkData(chunk_size) };
+                }
+                ChunkedDecoderState::ChunkData(remaining) => {
+                    if buf.is_empty() { return Ok(0) }
+                    let max_read: usize = std::cmp::min(buf.len() as u64, remaining) as usize;
+                    let bytes_read: usize = self.reader.read(&mut buf[..max_read])?;
+                    if bytes_read == 0 {
+                        return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "connection closed in the middle of a chunk"))
+                    }
+                    let remaining: u64 = remaining - bytes_read as u64;
+                    self.state = if remaining == 0 { ChunkedDecoderState::ChunkDataCrlf } else { ChunkedDecoderState::ChunkData(remaining) };
+                    return Ok(bytes_read);
+                }
+                ChunkedDecoderState::ChunkDataCrlf => {
+                    if !self.read_line(2)?.is_empty() { return Err(invalid_data("chunk-data is not followed by CRLF")) }
+                    self.state = ChunkedDecoderState::ChunkSize;
+                }
+                ChunkedDecoderState::TrailerSection => {
+                    // trailer-section = *( field-line CRLF ) - rfc9112#section-7.1.2
+                    let mut remaining_section_size: usize = self.max_section_size;
+                    loop {
+                        if remaining_section_size < 2 { return Err(invalid_data("trailer section is too large")) }
+                        let line: Vec<u8> = self.read_line(remaining_section_size)?;
+                        remaining_section_size -= line.len() + 2;
+                        if line.is_empty() { break }
+                        match super::parse_field_line(&line) {
+                            Ok((name, value)) => self.trailer_fields.append(name, value),
+                            Err(_) => return Err(invalid_data("invalid trailer field line")),
+                        }
+                    }
+                    self.state = ChunkedDecoderState::Done;
+                }
+                ChunkedDecoderState::Done => return Ok(0),
+            }
+        }
+    }
+}
+
+// Parses the chunk-size out of a chunk-size line, ignoring any chunk extensions
+// "A recipient MUST ignore unrecognized chunk extensions" - rfc9112#section-7.1.1
+fn parse_chunk_size(line: &[u8]) -> std::io::Result<u64> {
+    let chunk_size: &[u8] = match line.iter().position(|&b| b == b';') {
+        Some(pos) => line[..pos].trim_ascii_end(), // chunk-ext = *( BWS ";" BWS chunk-ext-name [ BWS "=" BWS chunk-ext-val ] )
+        None => line,
+    };
+    if chunk_size.is_empty() || chunk_size.len() > 16 || !chunk_size.iter().all(|b| b.is_ascii_hexdigit()) {
+        return Err(invalid_data("invalid chunk-size"))
+    }
+    let chunk_size: &str = std::str::from_utf8(chunk_size).map_err(|_| invalid_data("invalid chunk-size"))?;
+    u64::from_str_radix(chunk_size, 16).map_err(|_| invalid_data("invalid chunk-size"))
+}
+
+/// A streaming encoder that applies the chunked transfer coding to everything written to it.
+///
+/// Every (non-empty) write becomes one chunk. `finish` must be called once the body is complete,
+/// to send the last-chunk and the trailer section that end the chunked body.
+pub struct ChunkedEncoder<W: std::io::Write> {
+    writer: W,
+}
+
+impl<W: std::io::Write> ChunkedEncoder<W> {
+    pub fn new(writer: W) -> ChunkedEncoder<W> {
+        ChunkedEncoder { writer }
+    }
+
+    // Writes the last-chunk, the trailer fields and the final CRLF, returning the underlying writer
+    // trailer-section = *( field-line CRLF ) - rfc9112#section-7.1.2
+    pub fn finish(mut self, trailer_fields: &super::HeaderMap) -> std::io::Result<W> {
+        let mut end: Vec<u8> = b"0\r\n".to_vec();
+        for (name, value) in trailer_fields.iter() {
+            end.extend_from_slice(name);
+            end.extend_from_slice(b": ");
+            end.extend_from_slice(value);
+            end.extend_from_slice(b"\r\n");
+        }
+        end.extend_from_slice(b"\r\n");
+        self.writer.write_all(&end)?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for ChunkedEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // An empty chunk would be mistaken for the last-chunk, so there is nothing to send
+        if buf.is_empty() { return Ok(0) }
+        self.writer.write_all(format!("{:X}\r\n", buf.len()).as_bytes())?;
+        self.writer.write_all(buf)?;
+        self.writer.write_all(b"\r\n")?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn decode(chunked_body: &[u8]) -> std::io::Result<(Vec<u8>, crate::tcp::http::HeaderMap)> {
+        let mut chunked_decoder: ChunkedDecoder<&[u8]> = ChunkedDecoder::new(chunked_body, 1024);
+        let mut body: Vec<u8> = Vec::new();
+        chunked_decoder.read_to_end(&mut body)?;
+        Ok((body, chunked_decoder.into_trailer_fields()))
+    }
+
+    #[test]
+    fn test_chunked_decoder() {
+        let (body, trailer_fields) = decode(b"4\r\nWiki\r\n6;name=value\r\npedia \r\nE ; ext\r\nin \r\n\r\nchunks.\r\n0\r\nExpires: never\r\n\r\n").unwrap();
+        assert_eq!(body, b"Wikipedia in \r\n\r\nchunks.".to_vec());
+        assert_eq!(trailer_fields.get(b"Expires"), Some(&b"never"[..]));
+
+        let (body, trailer_fields) = decode(b"0\r\n\r\n").unwrap();
+        assert!(body.is_empty());
+        assert_eq!(trailer_fields, crate::tcp::http::HeaderMap::new());
+    }
+
+    #[test]
+    fn test_chunked_encoder() {
+        let mut chunked_encoder: ChunkedEncoder<Vec<u8>> = ChunkedEncoder::new(Vec::new());
+        std::io::Write::write_all(&mut chunked_encoder, b"Wiki").unwrap();
+        std::io::Write::write_all(&mut chunked_encoder, b"").unwrap(); // Must not end the body early
+        std::io::Write::write_all(&mut chunked_encoder, &b"a".repeat(26)).unwrap();
+        let mut trailer_fields: crate::tcp::http::HeaderMap = crate::tcp::http::HeaderMap::new();
+        trailer_fields.append(b"Expires", b"never");
+        let chunked_body: Vec<u8> = chunked_encoder.finish(&trailer_fields).unwrap();
+        assert_eq!(chunked_body, [b"4\r\nWiki\r\n1A\r\n".to_vec(), b"a".repeat(26), b"\r\n0\r\nExpires: never\r\n\r\n".to_vec()].concat());
+
+        // Whatever is encoded decodes back to the same body and trailer fields
+        let (body, decoded_trailer_fields) = decode(&chunked_body).unwrap();
+        assert_eq!(body, [b"Wiki".to_vec(), b"a".repeat(26)].concat());
+        assert_eq!(decoded_trailer_fields, trailer_fields);
+    }
+
+    #[test]
+    fn test_chunked_decoder_malformed() {
+        assert_eq!(decode(b"x\r\nWiki\r\n0\r\n\r\n").unwrap_err().kind(), std::io::ErrorKind::InvalidData);        // Not hex
+        assert_eq!(decode(b"4\r\nWikipedia\r\n0\r\n\r\n").unwrap_err().kind(), std::io::ErrorKind::InvalidData);   // Chunk longer than its size
+        assert_eq!(decode(b"4\nWiki\r\n0\r\n\r\n").unwrap_err().kind(), std::io::ErrorKind::InvalidData);          // Bare LF
+        assert_eq!(decode(b"11111111111111111\r\n").unwrap_err().kind(), std::io::ErrorKind::InvalidData);         // Overflowing chunk-size
+        assert_eq!(decode(b"0\r\nExpires never\r\n\r\n").unwrap_err().kind(), std::io::ErrorKind::InvalidData);    // Bad trailer field
+        assert_eq!(decode(b"4\r\nWi").unwrap_err().kind(), std::io::ErrorKind::UnexpectedEof);                     // Truncated
+    }
+}
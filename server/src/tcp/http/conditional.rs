@@ -0,0 +1,220 @@
+// tcp/http/conditional.rs
+
+// The validators of a representation, which let a client check whether the copy it has cached is still current - rfc9110#section-8.8
+pub struct Validators {
+    pub etag: Vec<u8>,                                // an entity-tag, quotes included (e.g., "\"2a-1f3c-65a0b2c4\""), strong unless weakened()
+    pub last_modified: Option<std::time::SystemTime>, // truncated to whole seconds, the precision of an HTTP date
+}
+
+impl Validators {
+    // The validators of a file, derived from its metadata so that the file never has to be read to compute them.
+    // The ETag changes whenever the file is replaced (inode), resized or written to (mtime, with the precision of the filesystem).
+    pub fn from_metadata(metadata: &std::fs::Metadata) -> Validators {
+        let modified: Option<std::time::Duration> = metadata.modified().ok().and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok());
+        let etag: String = format!("\"{:x}-{:x}-{:x}\"", inode(metadata), metadata.len(), modified.map_or(0, |modified| modified.as_nanos()));
+
+        // "An origin server SHOULD NOT send a Last-Modified date that is later than the server's time of message origination" - rfc9110#section-8.8.2.1
+        let now: std::time::Duration = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+        let last_modified: Option<std::time::SystemTime> = modified.map(|modified| std::time::UNIX_EPOCH + std::time::Duration::from_secs(std::cmp::min(modified, now).as_secs()));
+        Validators { etag: etag.into_bytes(), last_modified }
+    }
+
+    // The validators of the file's content sent with a content coding. The compressed octets differ from the file's, so they only get a weak ETag - rfc9110#section-8.8.3.3
+    pub fn weakened(&self) -> Validators {
+        let etag: Vec<u8> = if self.etag.starts_with(b"W/") { self.etag.clone() } else { [&b"W/"[..], &self.etag].concat() };
+        Validators { etag, last_modified: self.last_modified }
+    }
+
+    // Adds the ETag and Last-Modified header fields, which are sent with both a 200 and a 304 response
+    pub fn insert_into(&self, header_field_lines: &mut super::HeaderMap) {
+        header_field_lines.insert(b"ETag", self.etag.clone());
+        if let Some(last_modified) = self.last_modified {
+            header_field_lines.insert(b"Last-Modified", super::date::format_http_date(last_modified));
+        }
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &std::fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+#[cfg(not(unix))]
+fn inode(_metadata: &std::fs::Metadata) -> u64 {
+    0
+}
+
+// Evaluates the conditional header fields of a request for an existing representation, in the order given by rfc9110#section-13.2.2.
+// Returns the status code to answer with instead of the representation (304 Not Modified or 412 Precondition Failed), if any.
+pub fn evaluate_preconditions(http_request: &super::HttpRequest, validators: &Validators) -> Option<super::StatusCode> {
+    let header_field_lines: &super::HeaderMap = &http_request.header_field_lines;
+    let is_get_or_head: bool = matches!(http_request.start_line.method, super::Method::Get | super::Method::Head);
+
+    // "1. When recipient is the origin server and If-Match is present, evaluate the If-Match precondition ... if false, respond 412 (Precondition Failed)"
+    // "2. When recipient is the origin server, If-Match is not present, and If-Unmodified-Since is present, evaluate the If-Unmodified-Since precondition"
+    if let Some(if_match) = header_field_lines.get_combined(b"If-Match") {
+        if !if_match_passes(&if_match, &validators.etag) { return Some(super::StatusCode::PRECONDITION_FAILED) }
+    } else if let Some(if_unmodified_since) = header_field_lines.get(b"If-Unmodified-Since") {
+        // "A recipient MUST ignore the If-Unmodified-Since header field if the received field value is not a valid HTTP-date" - rfc9110#section-13.1.4
+        if let (Some(date), Some(last_modified)) = (super::date::parse_http_date(if_unmodified_since), validators.last_modified) {
+            if last_modified > date { return Some(super::StatusCode::PRECONDITION_FAILED) }
+        }
+    }
+
+    // "3. When If-None-Match is present, evaluate the If-None-Match precondition ... if false for GET/HEAD, respond 304 (Not Modified); if false for other methods, respond 412"
+    // "4. When the method is GET or HEAD, If-None-Match is not present, and If-Modified-Since is present, evaluate the If-Modified-Since precondition"
+    if let Some(if_none_match) = header_field_lines.get_combined(b"If-None-Match") {
+        if !if_none_match_passes(&if_none_match, &validators.etag) {
+            return Some(if is_get_or_head { super::StatusCode::NOT_MODIFIED } else { super::StatusCode::PRECONDITION_FAILED });
+        }
+    } else if let (true, Some(if_modified_since)) = (is_get_or_head, header_field_lines.get(b"If-Modified-Since")) {
+        // "A recipient MUST ignore the If-Modified-Since header field if the received field value is not a valid HTTP-date" - rfc9110#section-13.1.3
+        if let (Some(date), Some(last_modified)) = (super::date::parse_http_date(if_modified_since), validators.last_modified) {
+            if last_modified <= date { return Some(super::StatusCode::NOT_MODIFIED) }
+        }
+    }
+    None
+}
+
+// Whether a Range header field is to be honoured, which If-Range only allows if the representation is unchanged - rfc9110#section-13.1.5
+// If-Range = entity-tag / HTTP-date. There is no If-Range to fail if it wasn't sent.
+pub fn if_range_passes(http_request: &super::HttpRequest, validators: &Validators) -> bool {
+    let Some(if_range) = http_request.header_field_lines.get(b"If-Range") else { return true };
+    let if_range: &[u8] = if_range.trim_ascii();
+    // "A client MUST NOT generate an If-Range header field containing an entity tag that is marked as weak", and a weak one never matches the strong comparison
+    if if_range.starts_with(b"\"") || if_range.starts_with(b"W/") {
+        return matches!(parse_entity_tags(if_range).as_deref(), Some([(false, opaque_tag)]) if *opaque_tag == validators.etag);
+    }
+    // "If the HTTP-date validator provided exactly matches the Last-Modified field value for the selected representation, the condition is true"
+    match (super::date::parse_http_date(if_range), validators.last_modified) {
+        (Some(date), Some(last_modified)) => date == last_modified,
+        _ => false,
+    }
+}
+
+// If-Match = "*" / #entity-tag, evaluated with the strong comparison function - rfc9110#section-13.1.1
+// The one exception is the weak ETag of a compressed representation (see Validators::weakened), which matches itself when a client sends it back
+// exactly as it was received: it is derived from the file, just like the strong one, and only marked weak because the octets are generated.
+fn if_match_passes(if_match: &[u8], etag: &[u8]) -> bool {
+    if if_match.trim_ascii() == b"*" { return true } // the representation exists
+    let (etag_is_weak, etag_opaque_tag): (bool, &[u8]) = split_weak(etag);
+    match parse_entity_tags(if_match) {
+        Some(entity_tags) => entity_tags.iter().any(|(is_weak, opaque_tag)| *is_weak == etag_is_weak && *opaque_tag == etag_opaque_tag),
+        None => false, // an invalid field value can't match
+    }
+}
+
+// If-None-Match = "*" / #entity-tag, evaluated with the weak comparison function - rfc9110#section-13.1.2
+fn if_none_match_passes(if_none_match: &[u8], etag: &[u8]) -> bool {
+    if if_none_match.trim_ascii() == b"*" { return false } // the representation exists
+    let (_, etag_opaque_tag): (bool, &[u8]) = split_weak(etag);
+    match parse_entity_tags(if_none_match) {
+        Some(entity_tags) => !entity_tags.iter().any(|(_, opaque_tag)| *opaque_tag == etag_opaque_tag),
+        None => true,
+    }
+}
+
+// Splits one of our own entity-tags into (is weak, opaque-tag)
+fn split_weak(etag: &[u8]) -> (bool, &[u8]) {
+    match etag.strip_prefix(b"W/") {
+        Some(opaque_tag) => (true, opaque_tag),
+        None => (false, etag),
+    }
+}
+
+// Splits a list of entity-tags into (is weak, opaque-tag) pairs, or returns None if the list is invalid
+// entity-tag = [ weak ] opaque-tag, weak = %s"W/", opaque-tag = DQUOTE *etagc DQUOTE - rfc9110#section-8.8.3
+// An opaque-tag can contain commas, so the list can't simply be split on them.
+fn parse_entity_tags(value: &[u8]) -> Option<Vec<(bool, &[u8])>> {
+    let mut entity_tags: Vec<(bool, &[u8])> = Vec::new();
+    let mut rest: &[u8] = value;
+    loop {
+        rest = rest.trim_ascii_start();
+        // Empty list elements are allowed and ignored - rfc9110#section-5.6.1
+        if let Some(after_comma) = rest.strip_prefix(b",") { rest = after_comma; continue }
+        if rest.is_empty() { break }
+
+        let (is_weak, tag) = match rest.strip_prefix(b"W/") {
+            Some(tag) => (true, tag),
+            None => (false, rest),
+        };
+        if tag.first() != Some(&b'"') { return None }
+        let closing_quote: usize = tag[1..].iter().position(|&b| b == b'"')? + 1;
+        let opaque_tag: &[u8] = &tag[..=closing_quote];
+        // etagc = %x21 / %x23-7E / obs-text
+        if !opaque_tag[1..closing_quote].iter().all(|&b| b == 0x21 || b >= 0x23 && b != 0x7F) { return None }
+        entity_tags.push((is_weak, opaque_tag));
+
+        rest = tag[closing_quote + 1..].trim_ascii_start();
+        match rest.strip_prefix(b",") {
+            Some(after_comma) => rest = after_comma,
+            None if rest.is_empty() => break,
+            None => return None,
+        }
+    }
+    Some(entity_tags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{vec_u8_to_http_request, HttpRequest, ParserLimits, StatusCode};
+
+    #[test]
+    fn test_parse_entity_tags() {
+        assert_eq!(parse_entity_tags(b"\"xyzzy\""), Some(vec![(false, &b"\"xyzzy\""[..])]));
+        assert_eq!(parse_entity_tags(b"W/\"xyzzy\", \"r2d2,xxxx\" ,, \"\""), Some(vec![(true, &b"\"xyzzy\""[..]), (false, &b"\"r2d2,xxxx\""[..]), (false, &b"\"\""[..])]));
+        assert_eq!(parse_entity_tags(b""), Some(Vec::new()));
+        assert_eq!(parse_entity_tags(b"xyzzy"), None);
+        assert_eq!(parse_entity_tags(b"\"xyzzy"), None);
+        assert_eq!(parse_entity_tags(b"\"xyzzy\" \"r2d2\""), None);
+        assert_eq!(parse_entity_tags(b"w/\"xyzzy\""), None);
+    }
+
+    #[test]
+    fn test_evaluate_preconditions() {
+        let validators: Validators = Validators {
+            etag: b"\"abc\"".to_vec(),
+            last_modified: Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(784111777)), // Sun, 06 Nov 1994 08:49:37 GMT
+        };
+        let evaluate = |request_head: &str| {
+            let http_request: HttpRequest = vec_u8_to_http_request(format!("{}\r\nHost: localhost\r\n\r\n", request_head).into_bytes(), &ParserLimits::default()).unwrap();
+            evaluate_preconditions(&http_request, &validators)
+        };
+
+        assert_eq!(evaluate("GET / HTTP/1.1"), None);
+
+        // If-None-Match uses the weak comparison, and takes precedence over If-Modified-Since
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-None-Match: \"xyz\", W/\"abc\""), Some(StatusCode::NOT_MODIFIED));
+        assert_eq!(evaluate("HEAD / HTTP/1.1\r\nIf-None-Match: *"), Some(StatusCode::NOT_MODIFIED));
+        assert_eq!(evaluate("DELETE / HTTP/1.1\r\nIf-None-Match: \"abc\""), Some(StatusCode::PRECONDITION_FAILED));
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-None-Match: \"xyz\"\r\nIf-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT"), None);
+
+        // If-Modified-Since is only evaluated for GET and HEAD, and ignored if it isn't a valid HTTP-date
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT"), Some(StatusCode::NOT_MODIFIED));
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-Modified-Since: Sun, 06 Nov 1994 08:49:36 GMT"), None);
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-Modified-Since: yesterday"), None);
+        assert_eq!(evaluate("POST / HTTP/1.1\r\nContent-Length: 0\r\nIf-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT"), None);
+
+        // If-Match uses the strong comparison, and is evaluated before everything else
+        assert_eq!(evaluate("PUT / HTTP/1.1\r\nContent-Length: 0\r\nIf-Match: \"abc\""), None);
+        assert_eq!(evaluate("PUT / HTTP/1.1\r\nContent-Length: 0\r\nIf-Match: W/\"abc\""), Some(StatusCode::PRECONDITION_FAILED));
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-Match: \"xyz\"\r\nIf-None-Match: \"abc\""), Some(StatusCode::PRECONDITION_FAILED));
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-Match: *\r\nIf-Unmodified-Since: Thu, 01 Jan 1970 00:00:00 GMT"), None); // If-Unmodified-Since is ignored
+
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-Unmodified-Since: Sun, 06 Nov 1994 08:49:37 GMT"), None);
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-Unmodified-Since: Sun, 06 Nov 1994 08:49:36 GMT"), Some(StatusCode::PRECONDITION_FAILED));
+
+        // A compressed representation is matched by its weak ETag exactly as it was sent, and no longer by the strong one of the file
+        let validators: Validators = validators.weakened();
+        let evaluate = |request_head: &str| {
+            let http_request: HttpRequest = vec_u8_to_http_request(format!("{}\r\nHost: localhost\r\n\r\n", request_head).into_bytes(), &ParserLimits::default()).unwrap();
+            evaluate_preconditions(&http_request, &validators)
+        };
+        assert_eq!(validators.etag, b"W/\"abc\"");
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-Match: W/\"abc\""), None);
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-Match: \"abc\""), Some(StatusCode::PRECONDITION_FAILED));
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-None-Match: \"abc\""), Some(StatusCode::NOT_MODIFIED));
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nIf-None-Match: W/\"abc\""), Some(StatusCode::NOT_MODIFIED));
+    }
+}
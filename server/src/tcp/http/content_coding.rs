@@ -0,0 +1,312 @@
+// tcp/http/content_coding.rs
+
+// Bodies shorter than this are sent as they are. Compressing them saves next to nothing (gzip alone adds 18 bytes of framing).
+const MIN_COMPRESSED_BODY_SIZE: u64 = 1024;
+
+// Media types whose content is already compressed, so compressing it again only costs time
+const COMPRESSED_MEDIA_TYPES: [&str; 12] = [
+    "application/gzip",
+    "application/zip",
+    "application/zstd",
+    "application/x-7z-compressed",
+    "application/x-bzip2",
+    "application/x-rar-compressed",
+    "application/x-xz",
+    "application/pdf",
+    "application/wasm",
+    "application/octet-stream", // unknown content, which is more often than not an archive or a binary
+    "font/woff",
+    "font/woff2",
+];
+
+// The content codings the server can apply to a response - rfc9110#section-8.4.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentCoding {
+    Gzip,    // DEFLATE in the gzip file format - rfc1952
+    Deflate, // DEFLATE in the zlib data format - rfc1950 (not raw DEFLATE, despite the name)
+}
+
+impl std::fmt::Display for ContentCoding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContentCoding::Gzip => write!(f, "gzip"),
+            ContentCoding::Deflate => write!(f, "deflate"),
+        }
+    }
+}
+
+// Whether (and how) a response body is compressed. It is negotiated before the response is built, so that a 304 (Not Modified)
+// can carry the same ETag and Vary as the 200 (OK) it stands in for - rfc9110#section-15.4.5
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Never,                  // the body isn't worth compressing, whatever the client accepts
+    Identity,               // the body is worth compressing, but the client doesn't accept a content coding the server supports
+    Encoded(ContentCoding), // the body is compressed with this content coding
+}
+
+// Decides whether a body of this Content-Type and length (None if unknown) is compressed for the client: only a body of a compressible
+// media type that is long enough (or of unknown length), and only with a content coding the client accepts.
+pub fn negotiate_compression(http_request: &super::HttpRequest, content_type: &[u8], length: Option<u64>) -> Compression {
+    if length.is_some_and(|length| length < MIN_COMPRESSED_BODY_SIZE) || !is_compressible(content_type) { return Compression::Never }
+    match negotiate_content_coding(http_request) {
+        Some(content_coding) => Compression::Encoded(content_coding),
+        None => Compression::Identity,
+    }
+}
+
+// Applies the outcome of negotiate_compression to a response for the negotiated resource, whatever its status code.
+// Every such response varies by Accept-Encoding, but only the body of a 200 (OK) is compressed. A 304 (Not Modified) has no body to compress,
+// but gets the header fields that the 200 would have been sent with, while any other response (e.g., a 206 or 412) is otherwise sent as it is.
+// The compressed body's length isn't known until it has been written, so it is sent in chunks (or close-delimited to an HTTP/1.0 client).
+pub fn apply_compression(compression: Compression, http_response: &mut super::HttpResponse) {
+    if compression == Compression::Never || http_response.header_field_lines.contains_key(b"Content-Encoding") { return }
+
+    // Whether the response is compressed depends on Accept-Encoding, which caches have to know whether or not it is - rfc9110#section-12.5.5
+    http_response.header_field_lines.append(b"Vary", b"Accept-Encoding");
+    let status_code: super::StatusCode = http_response.start_line.status_code;
+    if status_code != super::StatusCode::OK && status_code != super::StatusCode::NOT_MODIFIED { return }
+    let Compression::Encoded(content_coding) = compression else { return };
+
+    if status_code == super::StatusCode::OK {
+        let Some(body) = http_response.body.take() else { return };
+        http_response.body = Some(super::HttpResponseBody::Encoded { body: Box::new(body), content_coding });
+        http_response.header_field_lines.insert(b"Content-Encoding", content_coding.to_string());
+    }
+    // The compressed representation differs octet for octet from the uncompressed one, so it can't share a strong ETag with it - rfc9110#section-8.8.3.3.
+    // A weak ETag still lets If-None-Match revalidate it, while If-Range and If-Match (which need a strong one) no longer match it.
+    if let Some(etag) = http_response.header_field_lines.get(b"ETag") {
+        if etag.starts_with(b"\"") {
+            let weak_etag: Vec<u8> = [&b"W/"[..], etag].concat();
+            http_response.header_field_lines.insert(b"ETag", weak_etag);
+        }
+    }
+}
+
+// Compresses the body of a 200 (OK) response that has already been built, if negotiate_compression decides to (see apply_compression)
+pub fn compress_response(http_request: &super::HttpRequest, http_response: &mut super::HttpResponse) {
+    if http_response.start_line.status_code != super::StatusCode::OK { return }
+    let (Some(body), Some(content_type)) = (&http_response.body, http_response.header_field_lines.get(b"Content-Type")) else { return };
+    let compression: Compression = negotiate_compression(http_request, content_type, body.length());
+    apply_compression(compression, http_response);
+}
+
+// Whether the body of a response with this Content-Type is worth compressing (i.e., isn't an image, audio, video or an already compressed format)
+fn is_compressible(content_type: &[u8]) -> bool {
+    let content_type: String = String::from_utf8_lossy(content_type).to_ascii_lowercase();
+    let media_type: &str = content_type.split(';').next().unwrap_or("").trim();
+    if media_type == "image/svg+xml" { return true } // an image, but as XML text
+    if ["image/", "audio/", "video/"].iter().any(|prefix| media_type.starts_with(prefix)) { return false }
+    !COMPRESSED_MEDIA_TYPES.contains(&media_type)
+}
+
+// Picks the content coding to apply from the Accept-Encoding of a request, or None to send the body as it is - rfc9110#section-12.5.3
+// The supported coding with the highest weight (qvalue) is used, gzip being preferred over deflate when they are weighted the same.
+// A client that sends no Accept-Encoding gets no content coding, as is usual, even though "any content coding is considered acceptable".
+fn negotiate_content_coding(http_request: &super::HttpRequest) -> Option<ContentCoding> {
+    let accept_encoding: Vec<u8> = http_request.header_field_lines.get_combined(b"Accept-Encoding")?;
+    let accept_encoding: String = String::from_utf8_lossy(&accept_encoding).to_ascii_lowercase();
+
+    // Accept-Encoding = #( codings [ weight ] ), codings = content-coding / "identity" / "*"
+    let mut weights: Vec<(&str, u16)> = Vec::new();
+    for element in accept_encoding.split(',') {
+        let mut parts = element.split(';').map(|part| part.trim());
+        let coding: &str = parts.next().unwrap_or("");
+        if coding.is_empty() { continue }
+        let weight: Option<u16> = match parts.find_map(|parameter| parameter.strip_prefix("q=")) {
+            Some(qvalue) => parse_qvalue(qvalue),
+            None => Some(1000),
+        };
+        let Some(weight) = weight else { continue }; // an element with an invalid weight is ignored as a whole
+        weights.push((coding, weight));
+    }
+    let weight_of = |names: &[&str]| -> Option<u16> {
+        weights.iter().find(|(coding, _)| names.contains(coding)).map(|(_, weight)| *weight)
+    };
+    // "If the representation's content coding is one of the content codings listed in the Accept-Encoding field value, then it is acceptable unless it is
+    // accompanied by a qvalue of 0" and "The asterisk "*" symbol in an Accept-Encoding field matches any available content coding not explicitly listed"
+    let wildcard_weight: Option<u16> = weight_of(&["*"]);
+    let gzip_weight: u16 = weight_of(&["gzip", "x-gzip"]).or(wildcard_weight).unwrap_or(0);
+    let deflate_weight: u16 = weight_of(&["deflate"]).or(wildcard_weight).unwrap_or(0);
+    // A client that weights identity above every supported coding gets the body as it is
+    let identity_weight: u16 = weight_of(&["identity"]).unwrap_or(0);
+
+    let (content_coding, weight): (ContentCoding, u16) = if gzip_weight >= deflate_weight { (ContentCoding::Gzip, gzip_weight) } else { (ContentCoding::Deflate, deflate_weight) };
+    if weight == 0 || weight < identity_weight { return None }
+    Some(content_coding)
+}
+
+// Parses a weight into thousandths (0 to 1000), or None if it isn't one - rfc9110#section-12.4.2
+// qvalue = ( "0" [ "." 0*3DIGIT ] ) / ( "1" [ "." 0*3("0") ] )
+pub(crate) fn parse_qvalue(qvalue: &str) -> Option<u16> {
+    let (integer, fraction): (&str, &str) = qvalue.split_once('.').unwrap_or((qvalue, ""));
+    if fraction.len() > 3 || !fraction.bytes().all(|byte| byte.is_ascii_digit()) { return None }
+    let thousandths: u16 = format!("{:0<3}", fraction).parse().ok()?;
+    match integer {
+        "0" => Some(thousandths),
+        "1" if thousandths == 0 => Some(1000),
+        _ => None,
+    }
+}
+
+/// Applies a content coding to everything written to it, writing the result to the underlying writer.
+///
+/// The framing (gzip header and trailer, or zlib header and checksum) is added around the DEFLATE stream.
+/// `finish` must be called once the whole body has been written, to complete the stream.
+pub struct ContentEncoder<W: std::io::Write> {
+    content_coding: ContentCoding,
+    deflate_encoder: super::deflate::DeflateEncoder<W>,
+    crc32: super::deflate::Crc32,
+    adler32: super::deflate::Adler32,
+    size: u32, // the length of the uncompressed data, modulo 2^32 (ISIZE)
+}
+
+impl<W: std::io::Write> ContentEncoder<W> {
+    pub fn new(mut writer: W, content_coding: ContentCoding) -> std::io::Result<ContentEncoder<W>> {
+        match content_coding {
+            // ID1, ID2, CM = 8 (deflate), FLG = 0, MTIME = 0 (unknown), XFL = 0, OS = 255 (unknown) - rfc1952#section-2.3
+            ContentCoding::Gzip => writer.write_all(&[0x1F, 0x8B, 8, 0, 0, 0, 0, 0, 0, 255])?,
+            // CMF = deflate with a 32K window, FLG = no dictionary, default compression level, with FCHECK making CMF * 256 + FLG a multiple of 31 - rfc1950#section-2.2
+            ContentCoding::Deflate => writer.write_all(&[0x78, 0x9C])?,
+        }
+        Ok(ContentEncoder {
+            content_coding,
+            deflate_encoder: super::deflate::DeflateEncoder::new(writer),
+            crc32: super::deflate::Crc32::new(),
+            adler32: super::deflate::Adler32::new(),
+            size: 0,
+        })
+    }
+
+    // Ends the DEFLATE stream and writes the trailer, returning the underlying writer
+    pub fn finish(self) -> std::io::Result<W> {
+        let mut writer: W = self.deflate_encoder.finish()?;
+        match self.content_coding {
+            // CRC32, ISIZE (both little-endian)
+            ContentCoding::Gzip => {
+                writer.write_all(&self.crc32.value().to_le_bytes())?;
+                writer.write_all(&self.size.to_le_bytes())?;
+            }
+            // ADLER32 (big-endian)
+            ContentCoding::Deflate => writer.write_all(&self.adler32.value().to_be_bytes())?,
+        }
+        Ok(writer)
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for ContentEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self.content_coding {
+            ContentCoding::Gzip => self.crc32.update(buf),
+            ContentCoding::Deflate => self.adler32.update(buf),
+        }
+        self.size = self.size.wrapping_add(buf.len() as u32);
+        self.deflate_encoder.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.deflate_encoder.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{construct_http_response, vec_u8_to_http_request, HttpRequest, HttpResponse, HttpResponseBody, ParserLimits};
+
+    fn http_request(accept_encoding: &str) -> HttpRequest {
+        vec_u8_to_http_request(format!("GET / HTTP/1.1\r\nHost: localhost\r\nAccept-Encoding: {}\r\n\r\n", accept_encoding).into_bytes(), &ParserLimits::default()).unwrap()
+    }
+
+    #[test]
+    fn test_negotiate_content_coding() {
+        let negotiate = |accept_encoding: &str| negotiate_content_coding(&http_request(accept_encoding));
+        assert_eq!(negotiate("gzip, deflate, br"), Some(ContentCoding::Gzip));
+        assert_eq!(negotiate("deflate"), Some(ContentCoding::Deflate));
+        assert_eq!(negotiate("gzip;q=0.5, deflate;q=0.8"), Some(ContentCoding::Deflate));
+        assert_eq!(negotiate("X-GZIP"), Some(ContentCoding::Gzip));
+        assert_eq!(negotiate("*"), Some(ContentCoding::Gzip));
+        assert_eq!(negotiate("*, gzip;q=0"), Some(ContentCoding::Deflate));
+        assert_eq!(negotiate("gzip;q=0, deflate;q=0"), None);
+        assert_eq!(negotiate("identity;q=1, gzip;q=0.5"), None);
+        assert_eq!(negotiate("br, zstd"), None);
+        // Weights outside of the qvalue grammar make their element invalid, so it is ignored (and gzip isn't accepted)
+        assert_eq!(negotiate("gzip;q=nan"), None);
+        assert_eq!(negotiate("gzip;q=inf, deflate;q=0.1"), Some(ContentCoding::Deflate));
+        assert_eq!(negotiate("gzip;q=1e5, deflate;q=0.1"), Some(ContentCoding::Deflate));
+        assert_eq!(negotiate("gzip;q=0.0001, deflate;q=0.001"), Some(ContentCoding::Deflate));
+        assert_eq!(negotiate("gzip;q=1., deflate;q=0.999"), Some(ContentCoding::Gzip));
+        assert_eq!(parse_qvalue("0"), Some(0));
+        assert_eq!(parse_qvalue("0.5"), Some(500));
+        assert_eq!(parse_qvalue("0.05"), Some(50));
+        assert_eq!(parse_qvalue("1.000"), Some(1000));
+        for invalid in ["", ".5", "1.001", "2", "-0", "+0.5", "0.5x", "00.5", "0.5 "] {
+            assert_eq!(parse_qvalue(invalid), None, "{:?}", invalid);
+        }
+        assert_eq!(negotiate(""), None);
+        assert_eq!(negotiate_content_coding(&vec_u8_to_http_request(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()).unwrap()), None);
+    }
+
+    #[test]
+    fn test_compress_response() {
+        let response = |content_type: &str, body: HttpResponseBody| {
+            let mut http_response: HttpResponse = construct_http_response(super::super::StatusCode::OK);
+            http_response.header_field_lines.insert(b"Content-Type", content_type);
+            http_response.header_field_lines.insert(b"ETag", b"\"abc\"");
+            http_response.body = Some(body);
+            http_response
+        };
+
+        let mut http_response: HttpResponse = response("text/html; charset=utf-8", HttpResponseBody::Bytes(vec![b'a'; 2000]));
+        compress_response(&http_request("gzip"), &mut http_response);
+        assert_eq!(http_response.header_field_lines.get(b"Content-Encoding"), Some(&b"gzip"[..]));
+        assert_eq!(http_response.header_field_lines.get(b"Vary"), Some(&b"Accept-Encoding"[..]));
+        assert_eq!(http_response.header_field_lines.get(b"ETag"), Some(&b"W/\"abc\""[..]));
+        assert_eq!(http_response.body.as_ref().unwrap().length(), None);
+
+        // A client that doesn't accept a supported coding still needs the Vary, small bodies and compressed media types are sent as they are
+        let mut http_response: HttpResponse = response("text/css", HttpResponseBody::Bytes(vec![b'a'; 2000]));
+        compress_response(&http_request("br"), &mut http_response);
+        assert_eq!(http_response.header_field_lines.get(b"Vary"), Some(&b"Accept-Encoding"[..]));
+        assert!(!http_response.header_field_lines.contains_key(b"Content-Encoding"));
+        for (content_type, length) in [("text/css", 10), ("image/png", 2000), ("font/woff2", 2000)] {
+            let mut http_response: HttpResponse = response(content_type, HttpResponseBody::Bytes(vec![b'a'; length]));
+            compress_response(&http_request("gzip"), &mut http_response);
+            assert!(!http_response.header_field_lines.contains_key(b"Vary") && !http_response.header_field_lines.contains_key(b"Content-Encoding"));
+        }
+
+        // A 304 gets the header fields of the compressed 200 it stands in for, any other status only gets the Vary
+        for (status_code, expected_etag) in [(super::super::StatusCode::NOT_MODIFIED, &b"W/\"abc\""[..]), (super::super::StatusCode::PARTIAL_CONTENT, &b"\"abc\""[..])] {
+            let mut http_response: HttpResponse = construct_http_response(status_code);
+            http_response.header_field_lines.insert(b"ETag", b"\"abc\"");
+            apply_compression(Compression::Encoded(ContentCoding::Gzip), &mut http_response);
+            assert_eq!(http_response.header_field_lines.get(b"ETag"), Some(expected_etag));
+            assert_eq!(http_response.header_field_lines.get(b"Vary"), Some(&b"Accept-Encoding"[..]));
+            assert!(!http_response.header_field_lines.contains_key(b"Content-Encoding"));
+        }
+        assert_eq!(negotiate_compression(&http_request("gzip"), b"text/html", None), Compression::Encoded(ContentCoding::Gzip));
+        assert_eq!(negotiate_compression(&http_request("br"), b"text/html", None), Compression::Identity);
+        assert_eq!(negotiate_compression(&http_request("gzip"), b"text/html", Some(10)), Compression::Never);
+
+        assert!(is_compressible(b"image/SVG+xml"));
+        assert!(is_compressible(b"application/json"));
+    }
+
+    #[test]
+    fn test_content_encoder() {
+        let encode = |content_coding: ContentCoding, data: &[u8]| {
+            let mut content_encoder: ContentEncoder<Vec<u8>> = ContentEncoder::new(Vec::new(), content_coding).unwrap();
+            std::io::Write::write_all(&mut content_encoder, data).unwrap();
+            content_encoder.finish().unwrap()
+        };
+
+        // "hello" compresses to the same DEFLATE stream either way, only the framing around it differs
+        let gzip: Vec<u8> = encode(ContentCoding::Gzip, b"hello");
+        assert_eq!(gzip[..10], [0x1F, 0x8B, 8, 0, 0, 0, 0, 0, 0, 255]);
+        assert_eq!(gzip[gzip.len() - 8..], [0x86, 0xA6, 0x10, 0x36, 5, 0, 0, 0]); // CRC-32 of "hello" and its length
+        let zlib: Vec<u8> = encode(ContentCoding::Deflate, b"hello");
+        assert_eq!((zlib[0] as u16 * 256 + zlib[1] as u16) % 31, 0);
+        assert_eq!(zlib[zlib.len() - 4..], [0x06, 0x2C, 0x02, 0x15]); // Adler-32 of "hello"
+        assert_eq!(gzip[10..gzip.len() - 8], zlib[2..zlib.len() - 4]);
+    }
+}
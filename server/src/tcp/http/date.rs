@@ -0,0 +1,127 @@
+// tcp/http/date.rs
+
+const DAY_NAMES: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"]; // 1970-01-01 was a Thursday
+const MONTH_NAMES: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+// Formats a point in time as an IMF-fixdate (e.g., "Sun, 06 Nov 1994 08:49:37 GMT"), the preferred format for HTTP dates - rfc9110#section-5.6.7
+// Times before 1970 are formatted as 1970-01-01 00:00:00, which is never reached by a server's clock in practice.
+pub fn format_http_date(time: std::time::SystemTime) -> String {
+    let seconds_since_epoch: u64 = match time.duration_since(std::time::UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs(),
+        Err(_) => 0,
+    };
+    let days_since_epoch: u64 = seconds_since_epoch / 86400;
+    let seconds_of_day: u64 = seconds_since_epoch % 86400;
+    let (year, month, day) = civil_from_days(days_since_epoch);
+
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAY_NAMES[(days_since_epoch % 7) as usize],
+        day,
+        MONTH_NAMES[(month - 1) as usize],
+        year,
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+// Parses an HTTP date in any of the three formats a recipient has to accept - rfc9110#section-5.6.7:
+//   IMF-fixdate:  Sun, 06 Nov 1994 08:49:37 GMT
+//   rfc850-date:  Sunday, 06-Nov-94 08:49:37 GMT
+//   asctime-date: Sun Nov  6 08:49:37 1994
+// Returns None for anything else (including dates before 1970), which the caller treats as if the field wasn't sent.
+pub fn parse_http_date(value: &[u8]) -> Option<std::time::SystemTime> {
+    let value: &str = std::str::from_utf8(value).ok()?;
+    let tokens: Vec<&str> = value.split_ascii_whitespace().collect();
+    let (day, month, year, time): (&str, &str, u64, &str) = match tokens[..] {
+        [day_name, day, month, year, time, "GMT"] if day_name.ends_with(',') && year.len() == 4 => (day, month, year.parse().ok()?, time),
+        [day_name, date, time, "GMT"] if day_name.ends_with(',') => {
+            let mut parts = date.split('-');
+            let (day, month, year) = (parts.next()?, parts.next()?, parts.next()?);
+            if parts.next().is_some() || year.len() != 2 { return None }
+            // "Recipients of a timestamp value in rfc850-date format, which uses a two-digit year, MUST interpret a timestamp
+            // that appears to be more than 50 years in the future as representing the most recent year in the past" (i.e., 1970-2069 here)
+            let year: u64 = year.parse().ok()?;
+            (day, month, if year < 70 { 2000 + year } else { 1900 + year }, time)
+        }
+        [_day_name, month, day, time, year] if year.len() == 4 => (day, month, year.parse().ok()?, time),
+        _ => return None,
+    };
+
+    if !(1..=2).contains(&day.len()) { return None }
+    let day: u64 = day.parse().ok()?;
+    let month: u64 = MONTH_NAMES.iter().position(|month_name| *month_name == month)? as u64 + 1;
+    let mut time_parts = time.split(':');
+    let (hour, minute, second) = (time_parts.next()?, time_parts.next()?, time_parts.next()?);
+    if time_parts.next().is_some() || [hour, minute, second].iter().any(|part| part.len() != 2) { return None }
+    let (hour, minute, second): (u64, u64, u64) = (hour.parse().ok()?, minute.parse().ok()?, second.parse().ok()?);
+    // A leap second (60) is allowed by the grammar, and simply lands on the next second
+    if year < 1970 || day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 { return None }
+
+    let seconds_since_epoch: u64 = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds_since_epoch))
+}
+
+// Converts a (year, month, day) date in the proleptic Gregorian calendar (from 1970 on) into a number of days since 1970-01-01
+// See, https://howardhinnant.github.io/date_algorithms.html#days_from_civil
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year: u64 = if month <= 2 { year - 1 } else { year };
+    let era: u64 = year / 400;
+    let year_of_era: u64 = year % 400;
+    let month_index: u64 = if month > 2 { month - 3 } else { month + 9 }; // 0 = March, ..., 11 = February
+    let day_of_year: u64 = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era: u64 = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+// Converts a number of days since 1970-01-01 into a (year, month, day) date in the proleptic Gregorian calendar
+// See, https://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days_since_epoch: u64) -> (u64, u64, u64) {
+    let days: u64 = days_since_epoch + 719468; // days since 0000-03-01
+    let era: u64 = days / 146097;
+    let day_of_era: u64 = days % 146097;
+    let year_of_era: u64 = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year: u64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100); // counting from March 1st
+    let month_index: u64 = (5 * day_of_year + 2) / 153; // 0 = March, ..., 11 = February
+    let day: u64 = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month: u64 = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year: u64 = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_http_date() {
+        let at = |seconds: u64| format_http_date(std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds));
+        assert_eq!(at(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(at(784111777), "Sun, 06 Nov 1994 08:49:37 GMT");  // The example from rfc9110#section-5.6.7
+        assert_eq!(at(951782400), "Tue, 29 Feb 2000 00:00:00 GMT");  // Leap day in a leap century
+        assert_eq!(at(1735689599), "Tue, 31 Dec 2024 23:59:59 GMT");
+    }
+
+    #[test]
+    fn test_parse_http_date() {
+        let at = |seconds: u64| Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(seconds));
+        // The three examples from rfc9110#section-5.6.7 all mean the same point in time
+        assert_eq!(parse_http_date(b"Sun, 06 Nov 1994 08:49:37 GMT"), at(784111777));
+        assert_eq!(parse_http_date(b"Sunday, 06-Nov-94 08:49:37 GMT"), at(784111777));
+        assert_eq!(parse_http_date(b"Sun Nov  6 08:49:37 1994"), at(784111777));
+        assert_eq!(parse_http_date(b"Sunday, 06-Nov-24 08:49:37 GMT"), parse_http_date(b"Wed, 06 Nov 2024 08:49:37 GMT"));
+
+        // Whatever is formatted parses back to the same second
+        for seconds in [0, 951782400, 1735689599, 4102444800] {
+            assert_eq!(parse_http_date(format_http_date(at(seconds).unwrap()).as_bytes()), at(seconds));
+        }
+
+        assert_eq!(parse_http_date(b""), None);
+        assert_eq!(parse_http_date(b"Sun, 06 Nov 1994 08:49:37 UTC"), None);
+        assert_eq!(parse_http_date(b"Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date(b"Sun, 06 Nov 1994 24:00:00 GMT"), None);
+        assert_eq!(parse_http_date(b"Sun, 06 Nov 1969 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date(b"1994-11-06T08:49:37Z"), None);
+    }
+}
@@ -0,0 +1,355 @@
+// tcp/http/deflate.rs
+
+// How far back a match can reach: "distances of up to 32K bytes" - rfc1951#section-2
+const WINDOW_SIZE: usize = 32 * 1024;
+// How much input is collected before it is compressed into a block. Bounds the memory of a streamed body to roughly WINDOW_SIZE + BLOCK_SIZE.
+const BLOCK_SIZE: usize = 64 * 1024;
+const MIN_MATCH_LENGTH: usize = 3;
+const MAX_MATCH_LENGTH: usize = 258;
+// How many earlier occurrences of the same 3 bytes are tried per position. Higher compresses (slightly) better but more slowly.
+const MAX_CHAIN_LENGTH: usize = 64;
+const HASH_BITS: u32 = 15;
+const NO_POSITION: usize = usize::MAX;
+
+// The base length (and number of extra bits) of each length code 257..=285 - rfc1951#section-3.2.5
+const LENGTH_BASES: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA_BITS: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+// The base distance (and number of extra bits) of each distance code 0..=29
+const DISTANCE_BASES: [u16; 30] = [1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577];
+const DISTANCE_EXTRA_BITS: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// A streaming DEFLATE (rfc1951) compressor that writes the compressed data to the underlying writer.
+///
+/// Repeated strings are found with LZ77 (hash chains over a 32 KiB window) and coded with the fixed Huffman codes,
+/// which need no code tables in the output and so can be written as soon as a block's input is known.
+/// `finish` must be called once all input has been written, to compress what is still buffered and end the stream.
+pub struct DeflateEncoder<W: std::io::Write> {
+    writer: W,
+    window: Vec<u8>,        // the last WINDOW_SIZE bytes already compressed (the history), followed by the input not yet compressed
+    history_length: usize,
+    bit_writer: BitWriter,
+}
+
+impl<W: std::io::Write> DeflateEncoder<W> {
+    pub fn new(writer: W) -> DeflateEncoder<W> {
+        DeflateEncoder { writer, window: Vec::new(), history_length: 0, bit_writer: BitWriter::default() }
+    }
+
+    // Compresses the remaining input as the final block, returning the underlying writer
+    pub fn finish(mut self) -> std::io::Result<W> {
+        let input_length: usize = self.window.len() - self.history_length;
+        self.compress_block(input_length, true)?;
+        self.bit_writer.align_to_byte();
+        self.writer.write_all(&std::mem::take(&mut self.bit_writer.bytes))?;
+        Ok(self.writer)
+    }
+
+    // Compresses the next input_length bytes of input as one block with fixed Huffman codes (BTYPE = 01) - rfc1951#section-3.2.6
+    fn compress_block(&mut self, input_length: usize, is_final: bool) -> std::io::Result<()> {
+        let end: usize = self.history_length + input_length;
+        self.bit_writer.write_bits(is_final as u32, 1); // BFINAL
+        self.bit_writer.write_bits(1, 2); // BTYPE
+
+        let data: &[u8] = &self.window[..end];
+        let mut match_finder: MatchFinder = MatchFinder::new(data);
+        for position in 0..self.history_length {
+            match_finder.insert(position);
+        }
+        let mut position: usize = self.history_length;
+        while position < end {
+            match match_finder.longest_match(position) {
+                Some((length, distance)) => {
+                    write_length(&mut self.bit_writer, length);
+                    write_distance(&mut self.bit_writer, distance);
+                    for matched_position in position..position + length {
+                        match_finder.insert(matched_position);
+                    }
+                    position += length;
+                }
+                None => {
+                    write_literal_length_code(&mut self.bit_writer, data[position] as u16);
+                    match_finder.insert(position);
+                    position += 1;
+                }
+            }
+        }
+        write_literal_length_code(&mut self.bit_writer, 256); // end-of-block
+
+        // Keep the last WINDOW_SIZE bytes as the history that the next block's matches can refer to
+        let history_start: usize = end.saturating_sub(WINDOW_SIZE);
+        self.window.drain(..history_start);
+        self.history_length = end - history_start;
+        self.writer.write_all(&std::mem::take(&mut self.bit_writer.bytes))
+    }
+}
+
+impl<W: std::io::Write> std::io::Write for DeflateEncoder<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.window.extend_from_slice(buf);
+        while self.window.len() - self.history_length >= BLOCK_SIZE {
+            self.compress_block(BLOCK_SIZE, false)?;
+        }
+        Ok(buf.len())
+    }
+
+    // Compressed data can only be written a block at a time, so only what is already compressed is flushed
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+// Finds earlier occurrences of the bytes at a position, through chains of the positions that start with the same 3 bytes (hashed)
+struct MatchFinder<'a> {
+    data: &'a [u8],
+    head: Vec<usize>, // the latest position for each hash
+    prev: Vec<usize>, // the previous position with the same hash, for each position
+}
+
+impl<'a> MatchFinder<'a> {
+    fn new(data: &'a [u8]) -> MatchFinder<'a> {
+        MatchFinder { data, head: vec![NO_POSITION; 1 << HASH_BITS], prev: vec![NO_POSITION; data.len()] }
+    }
+
+    fn hash(&self, position: usize) -> usize {
+        let bytes: &[u8] = &self.data[position..position + MIN_MATCH_LENGTH];
+        (((bytes[0] as usize) << 10 ^ (bytes[1] as usize) << 5 ^ bytes[2] as usize).wrapping_mul(2654435761) >> 7) & ((1 << HASH_BITS) - 1)
+    }
+
+    fn insert(&mut self, position: usize) {
+        if position + MIN_MATCH_LENGTH > self.data.len() { return }
+        let hash: usize = self.hash(position);
+        self.prev[position] = self.head[hash];
+        self.head[hash] = position;
+    }
+
+    // The (length, distance) of the longest earlier match for the bytes at position, if there is one of at least MIN_MATCH_LENGTH bytes
+    fn longest_match(&self, position: usize) -> Option<(usize, usize)> {
+        if position + MIN_MATCH_LENGTH > self.data.len() { return None }
+        let max_length: usize = std::cmp::min(MAX_MATCH_LENGTH, self.data.len() - position);
+        let mut best: Option<(usize, usize)> = None;
+        let mut candidate: usize = self.head[self.hash(position)];
+        let mut chain_length: usize = 0;
+        while candidate != NO_POSITION && position - candidate <= WINDOW_SIZE && chain_length < MAX_CHAIN_LENGTH {
+            let length: usize = self.data[candidate..].iter().zip(&self.data[position..position + max_length]).take_while(|(a, b)| a == b).count();
+            if length >= MIN_MATCH_LENGTH && best.is_none_or(|(best_length, _)| length > best_length) {
+                best = Some((length, position - candidate));
+                if length == max_length { break }
+            }
+            candidate = self.prev[candidate];
+            chain_length += 1;
+        }
+        best
+    }
+}
+
+// Writes a literal byte (0..=255), the end-of-block code (256) or a length code (257..=285) with the fixed literal/length Huffman code - rfc1951#section-3.2.6
+fn write_literal_length_code(bit_writer: &mut BitWriter, symbol: u16) {
+    let (code, code_length): (u32, u32) = match symbol {
+        0..=143 => (0x30 + symbol as u32, 8),
+        144..=255 => (0x190 + symbol as u32 - 144, 9),
+        256..=279 => (symbol as u32 - 256, 7),
+        _ => (0xC0 + symbol as u32 - 280, 8),
+    };
+    bit_writer.write_huffman_code(code, code_length);
+}
+
+fn write_length(bit_writer: &mut BitWriter, length: usize) {
+    let index: usize = LENGTH_BASES.iter().rposition(|&base| base as usize <= length).unwrap_or(0);
+    write_literal_length_code(bit_writer, 257 + index as u16);
+    bit_writer.write_bits((length - LENGTH_BASES[index] as usize) as u32, LENGTH_EXTRA_BITS[index] as u32);
+}
+
+// "Distance codes 0-31 are represented by (fixed-length) 5-bit codes"
+fn write_distance(bit_writer: &mut BitWriter, distance: usize) {
+    let index: usize = DISTANCE_BASES.iter().rposition(|&base| base as usize <= distance).unwrap_or(0);
+    bit_writer.write_huffman_code(index as u32, 5);
+    bit_writer.write_bits((distance - DISTANCE_BASES[index] as usize) as u32, DISTANCE_EXTRA_BITS[index] as u32);
+}
+
+// Packs bits into bytes starting with the least significant bit of each byte - rfc1951#section-3.1.1
+#[derive(Default)]
+struct BitWriter {
+    bit_buffer: u64,
+    bit_count: u32,
+    bytes: Vec<u8>,
+}
+
+impl BitWriter {
+    // Data elements (e.g., extra bits) are packed starting with their least significant bit
+    fn write_bits(&mut self, value: u32, count: u32) {
+        self.bit_buffer |= (value as u64) << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.bytes.push(self.bit_buffer as u8);
+            self.bit_buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    // "Huffman codes are packed starting with the most significant bit of the code"
+    fn write_huffman_code(&mut self, code: u32, code_length: u32) {
+        self.write_bits(code.reverse_bits() >> (32 - code_length), code_length);
+    }
+
+    fn align_to_byte(&mut self) {
+        if self.bit_count > 0 { self.write_bits(0, 8 - self.bit_count) }
+    }
+}
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table: [u32; 256] = [0; 256];
+    let mut n: usize = 0;
+    while n < 256 {
+        let mut c: u32 = n as u32;
+        let mut k: usize = 0;
+        while k < 8 {
+            c = if c & 1 == 1 { 0xEDB88320 ^ (c >> 1) } else { c >> 1 };
+            k += 1;
+        }
+        table[n] = c;
+        n += 1;
+    }
+    table
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+// The CRC-32 of a gzip member's uncompressed data, updated as the data is written - rfc1952#section-8
+#[derive(Debug, Clone, Copy)]
+pub struct Crc32(u32);
+
+impl Crc32 {
+    pub fn new() -> Crc32 {
+        Crc32(0xFFFFFFFF)
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.0 = CRC32_TABLE[((self.0 ^ byte as u32) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    pub fn value(&self) -> u32 {
+        !self.0
+    }
+}
+
+// The Adler-32 checksum of a zlib stream's uncompressed data, updated as the data is written - rfc1950#section-8.2
+#[derive(Debug, Clone, Copy)]
+pub struct Adler32 { a: u32, b: u32 }
+
+impl Adler32 {
+    pub fn new() -> Adler32 {
+        Adler32 { a: 1, b: 0 }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        // 5552 is the most bytes that can be summed before b could overflow a u32 and has to be reduced
+        for chunk in data.chunks(5552) {
+            for &byte in chunk {
+                self.a += byte as u32;
+                self.b += self.a;
+            }
+            self.a %= 65521;
+            self.b %= 65521;
+        }
+    }
+
+    pub fn value(&self) -> u32 {
+        self.b << 16 | self.a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A decoder for the blocks DeflateEncoder writes (fixed Huffman codes only), to check that what is compressed decompresses to the input
+    fn inflate_fixed(compressed: &[u8]) -> Vec<u8> {
+        let mut bit_position: usize = 0;
+        let mut read_bits = |count: usize| -> u32 {
+            let mut value: u32 = 0;
+            for i in 0..count {
+                value |= ((compressed[bit_position / 8] >> (bit_position % 8)) as u32 & 1) << i;
+                bit_position += 1;
+            }
+            value
+        };
+        let mut output: Vec<u8> = Vec::new();
+        loop {
+            let is_final: bool = read_bits(1) == 1;
+            assert_eq!(read_bits(2), 1, "only fixed Huffman blocks are written");
+            loop {
+                // Read the literal/length code one bit at a time (most significant bit first) until it is one of the fixed codes
+                let mut code: u32 = 0;
+                let mut code_length: u32 = 0;
+                let symbol: u32 = loop {
+                    code = code << 1 | read_bits(1);
+                    code_length += 1;
+                    match (code_length, code) {
+                        (7, 0..=0x17) => break code + 256,
+                        (8, 0x30..=0xBF) => break code - 0x30,
+                        (8, 0xC0..=0xC7) => break code - 0xC0 + 280,
+                        (9, 0x190..=0x1FF) => break code - 0x190 + 144,
+                        _ => assert!(code_length < 9),
+                    }
+                };
+                match symbol {
+                    0..=255 => output.push(symbol as u8),
+                    256 => break,
+                    _ => {
+                        let index: usize = symbol as usize - 257;
+                        let length: usize = LENGTH_BASES[index] as usize + read_bits(LENGTH_EXTRA_BITS[index] as usize) as usize;
+                        let distance_code: usize = (read_bits(5).reverse_bits() >> 27) as usize;
+                        let distance: usize = DISTANCE_BASES[distance_code] as usize + read_bits(DISTANCE_EXTRA_BITS[distance_code] as usize) as usize;
+                        for _ in 0..length {
+                            output.push(output[output.len() - distance]);
+                        }
+                    }
+                }
+            }
+            if is_final { return output }
+        }
+    }
+
+    fn deflate(input: &[u8], write_size: usize) -> Vec<u8> {
+        let mut deflate_encoder: DeflateEncoder<Vec<u8>> = DeflateEncoder::new(Vec::new());
+        for piece in input.chunks(write_size) {
+            std::io::Write::write_all(&mut deflate_encoder, piece).unwrap();
+        }
+        deflate_encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_deflate_encoder() {
+        assert_eq!(deflate(b"", 1), vec![0x03, 0x00]); // A final fixed block with just the end-of-block code
+        assert_eq!(inflate_fixed(&deflate(b"a", 1)), b"a");
+
+        // Repetitive input (e.g., HTML) shrinks, and input that spans several blocks can match the history of the previous block
+        let html: Vec<u8> = (0..10000).flat_map(|i| format!("<tr><td>row {}</td><td>{}</td></tr>\n", i, i * 7 % 13).into_bytes()).collect();
+        let compressed: Vec<u8> = deflate(&html, 1000);
+        assert!(html.len() > 3 * BLOCK_SIZE && compressed.len() < html.len() / 4);
+        assert_eq!(inflate_fixed(&compressed), html);
+        assert_eq!(deflate(&html, html.len()), compressed); // How the input is split up when it is written makes no difference
+
+        // Input without repetitions (every byte value, with a long run in the middle) still round-trips
+        let bytes: Vec<u8> = (0..=255).chain(std::iter::repeat_n(0, 1000)).chain((0..=255).rev()).collect();
+        assert_eq!(inflate_fixed(&deflate(&bytes, 7)), bytes);
+    }
+
+    #[test]
+    fn test_checksums() {
+        // The standard check values of both checksums
+        let mut crc32: Crc32 = Crc32::new();
+        crc32.update(b"12345");
+        crc32.update(b"6789");
+        assert_eq!(crc32.value(), 0xCBF43926);
+
+        let mut adler32: Adler32 = Adler32::new();
+        adler32.update(b"Wikipedia");
+        assert_eq!(adler32.value(), 0x11E60398);
+        let mut adler32: Adler32 = Adler32::new();
+        adler32.update(&[0xFF; 100000]); // Enough to overflow a u32 if b weren't reduced along the way
+        assert_eq!(adler32.value(), 0x149A302C);
+    }
+}
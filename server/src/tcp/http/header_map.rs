@@ -0,0 +1,102 @@
+// tcp/http/header_map.rs
+
+/// An ordered collection of field lines (i.e., a header or trailer section).
+///
+/// "Field names are case-insensitive" - rfc9110#section-5.1, so every lookup compares names
+/// ASCII case-insensitively while the original spelling is kept for output. Repeated fields
+/// (e.g., Set-Cookie) are kept as separate entries, in the order they were received or added.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HeaderMap {
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl HeaderMap {
+    pub fn new() -> HeaderMap {
+        HeaderMap { entries: Vec::new() }
+    }
+
+    // Returns the value of the first field line with the given name
+    pub fn get(&self, name: &[u8]) -> Option<&[u8]> {
+        self.entries.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_slice())
+    }
+
+    // Returns the values of every field line with the given name, in order
+    pub fn get_all<'a>(&'a self, name: &'a [u8]) -> impl Iterator<Item = &'a [u8]> + 'a {
+        self.entries.iter()
+            .filter(move |(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_slice())
+    }
+
+    // Returns every value for the given name joined into a single comma-separated list
+    // "A recipient MAY combine multiple field lines within a field section that have the same field name into one field line ... by appending each subsequent field line value to the initial field line value in order, separated by a comma" - rfc9110#section-5.3
+    pub fn get_combined(&self, name: &[u8]) -> Option<Vec<u8>> {
+        let values: Vec<&[u8]> = self.get_all(name).collect();
+        if values.is_empty() { return None }
+        Some(values.join(&b", "[..]))
+    }
+
+    pub fn contains_key(&self, name: &[u8]) -> bool {
+        self.get(name).is_some()
+    }
+
+    // Sets a field, replacing every existing field line with the same name
+    pub fn insert(&mut self, name: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
+        let name: Vec<u8> = name.into();
+        match self.entries.iter().position(|(key, _)| key.eq_ignore_ascii_case(&name)) {
+            Some(pos) => {
+                // keep the first field line (in place, so the order is stable) and drop the rest
+                let mut index: usize = 0;
+                self.entries.retain(|(key, _)| {
+                    let keep: bool = index <= pos || !key.eq_ignore_ascii_case(&name);
+                    index += 1;
+                    keep
+                });
+                self.entries[pos] = (name, value.into());
+            }
+            None => self.entries.push((name, value.into())),
+        }
+    }
+
+    // Adds a field line, keeping any existing field lines with the same name
+    pub fn append(&mut self, name: impl Into<Vec<u8>>, value: impl Into<Vec<u8>>) {
+        self.entries.push((name.into(), value.into()));
+    }
+
+    // Iterates over every field line, in order
+    pub fn iter(&self) -> impl Iterator<Item = (&[u8], &[u8])> {
+        self.entries.iter().map(|(key, value)| (key.as_slice(), value.as_slice()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header_map() {
+        let mut header_map: HeaderMap = HeaderMap::new();
+        header_map.append(b"Host", b"localhost:8000");
+        header_map.append(b"Accept", b"text/html");
+        header_map.append(b"accept", b"*/*");
+        header_map.append(b"Set-Cookie", b"a=1");
+
+        assert_eq!(header_map.get(b"host"), Some(&b"localhost:8000"[..]));      // Case-insensitive
+        assert_eq!(header_map.get(b"ACCEPT"), Some(&b"text/html"[..]));         // First value
+        assert_eq!(header_map.get_all(b"Accept").collect::<Vec<&[u8]>>(), vec![&b"text/html"[..], &b"*/*"[..]]);
+        assert_eq!(header_map.get_combined(b"Accept"), Some(b"text/html, */*".to_vec()));
+        assert_eq!(header_map.get_combined(b"Content-Length"), None);
+        assert!(!header_map.contains_key(b"Host "));
+
+        header_map.insert(b"ACCEPT", b"application/json"); // Replaces both Accept field lines, in place
+        header_map.insert(b"Server", b"rust-std-web-server");
+        let field_lines: Vec<(&[u8], &[u8])> = header_map.iter().collect();
+        assert_eq!(field_lines, vec![
+            (&b"Host"[..], &b"localhost:8000"[..]),
+            (&b"ACCEPT"[..], &b"application/json"[..]),
+            (&b"Set-Cookie"[..], &b"a=1"[..]),
+            (&b"Server"[..], &b"rust-std-web-server"[..]),
+        ]);
+    }
+}
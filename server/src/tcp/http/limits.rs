@@ -0,0 +1,35 @@
+// tcp/http/limits.rs
+
+/// Upper bounds on the size of each part of a request.
+///
+/// "HTTP does not place a predefined limit on the length of a request-line" - rfc9112#section-3, and the same is true of the header
+/// section and the message body, so a server has to choose its own. Anything over a limit is rejected with the status code the RFCs
+/// set aside for it: 414 (URI Too Long) for the request-line, 431 (Request Header Fields Too Large) for the header section and
+/// 413 (Content Too Large) for the message body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParserLimits {
+    pub max_request_line_length: usize, // "It is RECOMMENDED that all HTTP senders and recipients support, at a minimum, request-line lengths of 8000 octets"
+    pub max_header_count: usize,        // the number of field lines in the header section (obs-fold continuations are part of the line they continue)
+    pub max_field_line_length: usize,   // a single field line, name and value included
+    pub max_header_section_size: usize, // the whole message head (request-line + header section); also used for the trailer section of a chunked body
+    pub max_body_size: u64,             // the decoded message body, whether it is framed by Content-Length or the chunked transfer coding
+}
+
+impl Default for ParserLimits {
+    fn default() -> ParserLimits {
+        ParserLimits {
+            max_request_line_length: 8 * 1024,
+            max_header_count: 100,
+            max_field_line_length: 8 * 1024,
+            max_header_section_size: 16 * 1024, // Browsers with a lot of cookies regularly send well over 1 KiB of headers
+            max_body_size: 10 * 1024 * 1024,    // 10 MiB
+        }
+    }
+}
+
+impl ParserLimits {
+    // Logs which limit a request ran into, so deployments can tell whether a limit needs raising
+    pub fn log_exceeded(limit_name: &str, limit: u64) {
+        println!("WARNING (PARSER_LIMITS): Request rejected, {} ({}) exceeded", limit_name, limit);
+    }
+}
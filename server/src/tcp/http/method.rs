@@ -0,0 +1,75 @@
+// tcp/http/method.rs
+
+// "The method token indicates the request method to be performed on the target resource. The request method is case-sensitive." - rfc9112#section-3.1
+// Every method registered in rfc9110#section-9 (plus PATCH, rfc5789) has its own variant. Any other token is kept as an Extension method.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Connect,
+    Options,
+    Trace,
+    Patch,
+    Extension(Vec<u8>),
+}
+
+pub const HTTP_METHODS: [Method; 9] = [
+    Method::Get,
+    Method::Head,
+    Method::Post,
+    Method::Put,
+    Method::Delete,
+    Method::Connect,
+    Method::Options,
+    Method::Trace,
+    Method::Patch,
+];
+pub const SUPPORTED_HTTP_METHODS: [Method; 6] = [
+    Method::Get,
+    Method::Head,
+    Method::Post,
+    Method::Put,
+    Method::Delete,
+    Method::Options,
+];
+
+impl Method {
+    // method = token - rfc9112#section-3.1
+    // Anything that is not a token is a BadRequest; a token that is not a registered method becomes an Extension method
+    pub fn parse(method: &[u8]) -> Result<Method, super::HttpRequestError> {
+        if !super::is_token(method) { return Err(super::HttpRequestError::BadRequest) }
+        match HTTP_METHODS.iter().find(|http_method| http_method.as_bytes() == method) {
+            Some(http_method) => Ok(http_method.clone()),
+            None => Ok(Method::Extension(method.to_vec())),
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            Method::Get => b"GET",
+            Method::Head => b"HEAD",
+            Method::Post => b"POST",
+            Method::Put => b"PUT",
+            Method::Delete => b"DELETE",
+            Method::Connect => b"CONNECT",
+            Method::Options => b"OPTIONS",
+            Method::Trace => b"TRACE",
+            Method::Patch => b"PATCH",
+            Method::Extension(method) => method,
+        }
+    }
+
+    // Checks if this server supports the method
+    pub fn is_supported(&self) -> bool {
+        SUPPORTED_HTTP_METHODS.contains(self)
+    }
+}
+
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", String::from_utf8_lossy(self.as_bytes()))
+    }
+}
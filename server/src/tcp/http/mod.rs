@@ -1,195 +1,446 @@
 // tcp/http/mod.rs
 
+mod body;
+mod chunked;
+mod conditional;
+mod content_coding;
+mod date;
+mod deflate;
+mod header_map;
+mod limits;
+mod method;
+mod range;
+mod request_target;
+mod status_code;
+mod uri;
+mod version;
+pub use body::{ChunkGenerator, HttpResponseBody};
+pub use chunked::{ChunkedDecoder, ChunkedEncoder};
+pub use conditional::{evaluate_preconditions, if_range_passes, Validators};
+pub use content_coding::{apply_compression, compress_response, negotiate_compression, Compression, ContentCoding, ContentEncoder};
+pub(crate) use content_coding::parse_qvalue;
+pub use date::format_http_date;
+pub use header_map::HeaderMap;
+pub use limits::ParserLimits;
+pub use method::{Method, SUPPORTED_HTTP_METHODS};
+pub use range::{evaluate_range, partial_content_response, unsatisfied_content_range, RangeResponse};
+pub use request_target::{Host, RequestTarget};
+pub use status_code::StatusCode;
+pub use uri::{encode_path, RequestUri};
+pub use version::Version;
+
+// Sent in the Server header field of every response - rfc9110#section-10.2.4
+pub const SERVER_NAME: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
 // TODO: "In practice, servers are implemented to only expect a request (a response is interpreted as an unknown or invalid request method)" - rfc9112#section-2.1
 
 #[derive(Debug)]
-pub enum HttpRequestError { BadRequest, UnsupportedMethod, UnsupportedVersion, InvalidHeader }
-
-pub const HTTP_METHODS: [&[u8]; 9] = [ b"GET", b"HEAD", b"POST", b"PUT", b"DELETE", b"CONNECT", b"OPTIONS", b"TRACE", b"PATCH" ];
-pub const SUPPORTED_HTTP_METHODS: [&[u8]; 5] = [
-    HTTP_METHODS[0], // b"GET"
-    HTTP_METHODS[1], // b"HEAD"
-    HTTP_METHODS[2], // b"POST"
-    HTTP_METHODS[3], // b"PUT"
-    HTTP_METHODS[4], // b"DELETE"
-];
-pub const HTTP_VERSIONS: [[u8; 8]; 4] = [*b"HTTP/1.0", *b"HTTP/1.1", *b"HTTP/2.0", *b"HTTP/3.0"];
-pub const SUPPORTED_HTTP_VERSIONS: [[u8; 8]; 1] = [*b"HTTP/1.1"];
+pub enum HttpRequestError { BadRequest, UnsupportedMethod, UnsupportedVersion, InvalidHeader, ContentTooLarge, UnsupportedTransferCoding, ExpectationFailed, UriTooLong, HeaderFieldsTooLarge }
 
 // ----- START HttpMessage - rfc9112#section-2.1 -----
 
 // Request - rfc9112#section-3
 pub struct HttpRequestLine {
-    pub method: Vec<u8>,
-    pub request_target: Vec<u8>,
-    pub http_version: Vec<u8>,
+    pub method: Method,
+    pub request_target: RequestTarget,
+    pub http_version: Version,
 }
 
 pub struct HttpRequest {
     pub start_line: HttpRequestLine,
-    pub header_field_lines: std::collections::HashMap<Vec<u8>, Vec<u8>>, // "zero or more header field lines"
-    pub body: Option<Vec<u8>>,                                           // "optional message body"
+    pub uri: Option<RequestUri>,       // the decoded path and query of an origin-form or absolute-form request-target
+    pub host: Option<Host>,            // the validated Host header field (None if it was empty)
+    pub header_field_lines: HeaderMap, // "zero or more header field lines"
+    pub body: Option<Vec<u8>>,         // "optional message body"
+    pub trailer_fields: HeaderMap,     // sent after a chunked message body - rfc9112#section-7.1.2
 }
 
 // Response - rfc9112#section-4
 pub struct HttpStatusLine {
-    pub http_version: Vec<u8>,
-    pub status_code: Vec<u8>,
-    pub reason_phrase: Vec<u8>,
+    pub http_version: Version,
+    pub status_code: StatusCode, // the reason-phrase is the status code's canonical one - StatusCode::reason_phrase()
 }
 
 pub struct HttpResponse {
     pub start_line: HttpStatusLine,
-    pub header_field_lines: std::collections::HashMap<Vec<u8>, Vec<u8>>, // "zero or more header field lines"
-    pub body: Option<Vec<u8>>,                                           // "optional message body"
-}
-
-// ----- END HttpMessage - rfc9112#section-2.1 -----
-
-pub fn is_http_request_method(potential_http_request_method: &[u8]) -> bool {
-    // "The method token is case-sensitive..." - rfc9110#section-9
-    if HTTP_METHODS.contains(&potential_http_request_method) { return true }
-    false
+    pub header_field_lines: HeaderMap,  // "zero or more header field lines"
+    pub body: Option<HttpResponseBody>, // "optional message body", streamed out when the response is written
 }
 
-// Checks if this server supports the HTTP method passed to it.
-pub fn is_supported_http_request_method(http_request_method: &[u8]) -> bool {
-    if SUPPORTED_HTTP_METHODS.contains(&http_request_method) { return true }
-    false
-}
+impl HttpRequest {
+    // Whether the connection should be kept open once this request has been answered - rfc9112#section-9.3
+    pub fn is_persistent_connection(&self) -> bool {
+        let connection_options: Vec<Vec<u8>> = match self.header_field_lines.get_combined(b"Connection") {
+            Some(connection) => connection.split(|&b| b == b',').map(|option| option.trim_ascii().to_ascii_lowercase()).collect(),
+            None => Vec::new(),
+        };
+        // "If the "close" connection option is present, the connection will not persist after the current response"
+        if connection_options.iter().any(|option| option == b"close") { return false }
+        // "If the received protocol is HTTP/1.1 (or later), the connection will persist after the current response"
+        if self.start_line.http_version != Version::HTTP_1_0 { return true }
+        // "If the received protocol is HTTP/1.0, the "keep-alive" connection option is present ... the connection will persist"
+        connection_options.iter().any(|option| option == b"keep-alive")
+    }
 
-pub fn is_valid_http_request_uri(potential_http_uri: &[u8]) -> bool {
-    // TODO: Improve the implementation here
-    if potential_http_uri[0] == b'/' { return true }
-    false
-}
+    // Reconstructs the target URI of the request (e.g., "http://localhost:8000/index.html?v=2") - rfc9112#section-3.3
+    //
+    // * `scheme` - The scheme of the listener the request arrived on (e.g., "http")
+    // * `default_authority` - The server's own authority, used when the request has no (non-empty) Host
+    pub fn effective_request_uri(&self, scheme: &str, default_authority: &str) -> String {
+        // "If the request-target is in absolute-form, the target URI is the same as the request-target"
+        let (path, query): (&[u8], &Option<Vec<u8>>) = match &self.start_line.request_target {
+            RequestTarget::Absolute { scheme, authority, path, query } => {
+                let query: String = query.as_ref().map_or(String::new(), |query| format!("?{}", String::from_utf8_lossy(query)));
+                return format!("{}://{}{}{}", String::from_utf8_lossy(scheme), String::from_utf8_lossy(authority), String::from_utf8_lossy(path), query)
+            }
+            RequestTarget::Origin { path, query } => (path, query),
+            // "If the request-target is in authority-form or asterisk-form, the target URI's ... path and query are empty"
+            RequestTarget::Authority { host, port } => return format!("{}://{}:{}", scheme, String::from_utf8_lossy(host), port),
+            RequestTarget::Asterisk => (b"", &None),
+        };
 
-pub fn is_http_version(potential_http_version: &[u8]) -> bool {
-    for http_version in HTTP_VERSIONS.iter() {
-        if potential_http_version == http_version { return true }
+        // "the target URI's authority component is the field value of the Host header field. If there is no Host header field or if its
+        // field value is empty or invalid, the target URI's authority component is empty" - we fall back to the server's own authority instead
+        let authority: String = match &self.host {
+            Some(host) => host.to_string(),
+            None => default_authority.to_string(),
+        };
+        let query: String = query.as_ref().map_or(String::new(), |query| format!("?{}", String::from_utf8_lossy(query)));
+        format!("{}://{}{}{}", scheme, authority, String::from_utf8_lossy(path), query)
     }
-    false
 }
 
-pub fn is_supported_http_version(http_version: &[u8]) -> bool {
-    for supported_http_version in SUPPORTED_HTTP_VERSIONS.iter() {
-        if http_version == supported_http_version { return true }
-    }
-    false
-}
+// ----- END HttpMessage - rfc9112#section-2.1 -----
 
-// Construct a HttpRequest from a Vec<u8>. Will return an error if any parts of the passed Vec<u8> do not form a valid HTTP Request.
+// Construct a HttpRequest from a Vec<u8>. Will return an error if any parts of the passed Vec<u8> do not form a valid HTTP Request,
+// or if the request-line or header section is larger than parser_limits allows.
 // TODO: Will also return an error if we are passed an HTTP response. (make sure this is true and add it to the test cases)
 
-pub fn vec_u8_to_http_request(buffer: Vec<u8>) -> Result<HttpRequest, HttpRequestError> {
+pub fn vec_u8_to_http_request(buffer: Vec<u8>, parser_limits: &ParserLimits) -> Result<HttpRequest, HttpRequestError> {
+    if buffer.len() > parser_limits.max_header_section_size {
+        ParserLimits::log_exceeded("max_header_section_size", parser_limits.max_header_section_size as u64);
+        return Err(HttpRequestError::HeaderFieldsTooLarge) // Send 431
+    }
 
     // ----- REQUEST LINE -----
     let crlf_index: usize = match buffer.windows(2).position(|window| window == b"\r\n") {
         Some(crlf_index) => crlf_index,
         None => return Err(HttpRequestError::BadRequest) // No CRLF -> send 400
     };
+    // "A server that receives a request-target longer than any URI it wishes to parse MUST respond with a 414 (URI Too Long) status code" - rfc9112#section-3
+    if crlf_index > parser_limits.max_request_line_length {
+        ParserLimits::log_exceeded("max_request_line_length", parser_limits.max_request_line_length as u64);
+        return Err(HttpRequestError::UriTooLong) // Send 414
+    }
 
     let request_line: &[u8] = &buffer[..crlf_index]; // we want this to be a & so we don't replicate the buffer into a new array
+    // request-line = method SP request-target SP HTTP-version, all of which are visible US-ASCII - rfc9112#section-3
+    if !request_line.iter().all(|&byte| byte == b' ' || byte.is_ascii_graphic()) { return Err(HttpRequestError::BadRequest) }
     let request_line_parts: Vec<&[u8]> = request_line.split(|&b| b == b' ').collect();
     if request_line_parts.len() != 3 { return Err(HttpRequestError::BadRequest) } // Malformed request line -> send 400
 
     // ----- method
-    let method: Vec<u8> =
-        if is_http_request_method(request_line_parts[0]) {
-            if is_supported_http_request_method(request_line_parts[0]) { request_line_parts[0].to_vec() }
-            else { return Err(HttpRequestError::UnsupportedMethod) } // Is an HTTP method, not a supported HTTP method -> send 501
-        }
-        else { return Err(HttpRequestError::BadRequest) }; // Not an HTTP method -> send 400
+    let method: Method = Method::parse(request_line_parts[0])?; // Not a token -> send 400
+    // "An origin server that receives a request method that is unrecognized or not implemented SHOULD respond with the 501 (Not Implemented) status code" - rfc9110#section-9.1
+    if !method.is_supported() { return Err(HttpRequestError::UnsupportedMethod) } // Is a method (registered or an extension), not a supported one -> send 501
 
     // ----- request-target
-    let request_target: Vec<u8> =
-        if is_valid_http_request_uri(request_line_parts[1]) { request_line_parts[1].to_vec() }
-        else { return Err(HttpRequestError::BadRequest) }; // Send 400 Bad Request
+    let request_target: RequestTarget = RequestTarget::parse(&method, request_line_parts[1])?; // Not a valid request-target (for this method) -> send 400
 
     // ----- HTTP-version
-    let http_version: Vec<u8> =
-        if is_http_version(request_line_parts[2]) {
-            if is_supported_http_version(request_line_parts[2]) { request_line_parts[2].to_vec() }
-            else { return Err(HttpRequestError::UnsupportedVersion) } // Is an HTTP version, not a supported HTTP version -> Send 505
-        }
-        else { return Err(HttpRequestError::BadRequest) }; // Not an HTTP version -> Send 400
+    let http_version: Version = match Version::parse(request_line_parts[2]) {
+        Some(http_version) if http_version.is_supported() => http_version,
+        // "A recipient that receives a message with a major version number that it implements and a minor version number higher than what
+        // it implements SHOULD process the message as if it were in the highest minor version within that major version" - rfc9110#section-6.2
+        Some(http_version) if http_version.major == Version::HTTP_1_1.major && http_version.minor > Version::HTTP_1_1.minor => Version::HTTP_1_1,
+        Some(_) => return Err(HttpRequestError::UnsupportedVersion), // Is an HTTP version, not a supported HTTP version -> Send 505
+        None => return Err(HttpRequestError::BadRequest), // Not an HTTP version -> Send 400
+    };
 
     // Construct HttpRequestLine
     let http_request_line: HttpRequestLine = HttpRequestLine {
-        method: method,
-        request_target: request_target,
-        http_version: http_version,
+        method,
+        request_target,
+        http_version,
     };
 
     // ----- HEADER FIELDS -----
-    // --- START FROM COPILOT
-    let headers_start = crlf_index + 2; // Skip the CRLF after the request line
-    let headers_end = match buffer[headers_start..].windows(4).position(|window| window == b"\r\n\r\n") {
-        Some(pos) => headers_start + pos,
+    // The header section runs from the end of the request line up to (and including the CRLF of) the last field line
+    let headers_start: usize = crlf_index + 2; // Skip the CRLF after the request line
+    let headers_end: usize = match buffer[crlf_index..].windows(4).position(|window| window == b"\r\n\r\n") {
+        Some(pos) => crlf_index + pos + 2,
         None => return Err(HttpRequestError::BadRequest),
     };
 
-    let headers_section = &buffer[headers_start..headers_end];
-    let headers_lines: Vec<&[u8]> = headers_section.split(|&b| b == b'\r' || b == b'\n').filter(|line| !line.is_empty()).collect();
+    let mut field_lines: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+    for line in split_crlf_lines(&buffer[headers_start..headers_end]) {
+        // Request Header Fields Too Large - rfc6585#section-5
+        if line.len() > parser_limits.max_field_line_length {
+            ParserLimits::log_exceeded("max_field_line_length", parser_limits.max_field_line_length as u64);
+            return Err(HttpRequestError::HeaderFieldsTooLarge) // Send 431
+        }
+
+        // obs-fold = OWS CRLF RWS - rfc9112#section-5.2
+        // "A server that receives an obs-fold in a request message ... MUST either reject the message by sending a 400 (Bad Request) ...
+        // or replace each received obs-fold with one or more SP octets prior to interpreting the field value"
+        if line.starts_with(b" ") || line.starts_with(b"\t") {
+            let continuation: &[u8] = trim_ows(line);
+            if !is_field_value(continuation) { return Err(HttpRequestError::InvalidHeader) }
+            match field_lines.last_mut() {
+                Some((key, value)) => {
+                    if !value.is_empty() && !continuation.is_empty() { value.push(b' ') }
+                    value.extend_from_slice(continuation);
+                    if key.len() + 2 + value.len() > parser_limits.max_field_line_length { // the unfolded field line, as "name: value"
+                        ParserLimits::log_exceeded("max_field_line_length", parser_limits.max_field_line_length as u64);
+                        return Err(HttpRequestError::HeaderFieldsTooLarge) // Send 431
+                    }
+                }
+                None => return Err(HttpRequestError::BadRequest), // Whitespace between the start-line and the first header field - rfc9112#section-2.2
+            }
+            continue;
+        }
 
-    let mut http_header_fields: std::collections::HashMap<Vec<u8>, Vec<u8>> = std::collections::HashMap::new();
-    for line in headers_lines {
-        if let Some(pos) = line.windows(2).position(|window| window == b": ") {
-            let key = &line[..pos];
-            let value = &line[pos + 2..];
-            http_header_fields.insert(key.to_vec(), value.to_vec());
+        if field_lines.len() == parser_limits.max_header_count {
+            ParserLimits::log_exceeded("max_header_count", parser_limits.max_header_count as u64);
+            return Err(HttpRequestError::HeaderFieldsTooLarge) // Send 431
         }
-        else { return Err(HttpRequestError::InvalidHeader) }
+        let (key, value) = parse_field_line(line)?;
+        field_lines.push((key.to_vec(), value.to_vec()));
+    }
+
+    let mut http_header_fields: HeaderMap = HeaderMap::new();
+    for (key, value) in field_lines {
+        http_header_fields.append(key, value);
     }
 
+    // ----- Host - rfc9112#section-3.2
+    // "A server MUST respond with a 400 (Bad Request) status code to any HTTP/1.1 request message that lacks a Host header field and
+    // to any request message that contains more than one Host header field line or a Host header field with an invalid field value."
+    let host: Option<Host> = {
+        let mut host_field_values = http_header_fields.get_all(b"Host");
+        match (host_field_values.next(), host_field_values.next()) {
+            (Some(host_field_value), None) => Host::parse(host_field_value)?,
+            (None, _) if http_request_line.http_version == Version::HTTP_1_0 => None, // Host is optional in HTTP/1.0
+            _ => return Err(HttpRequestError::BadRequest), // No Host, or more than one Host -> send 400
+        }
+    };
+
+    // ----- decoded path and query (only the origin-form and absolute-form have them)
+    let uri: Option<RequestUri> = match &http_request_line.request_target {
+        RequestTarget::Origin { path, query } | RequestTarget::Absolute { path, query, .. } => Some(RequestUri::parse(path, query.as_deref())?),
+        RequestTarget::Authority { .. } | RequestTarget::Asterisk => None,
+    };
+
     // Construct HttpRequest
     let http_request: HttpRequest = HttpRequest {
         start_line: http_request_line,
+        uri,
+        host,
         header_field_lines: http_header_fields,
-        body: Some(buffer[headers_end + 4..].to_vec()), // None,
+        body: None, // the message body (if any) is read separately, see message_body_length()
+        trailer_fields: HeaderMap::new(),
     };
-        // --- END FROM COPILOT
 
+    Ok(http_request)
+}
+
+// tchar = "!" / "#" / "$" / "%" / "&" / "'" / "*" / "+" / "-" / "." / "^" / "_" / "`" / "|" / "~" / DIGIT / ALPHA - rfc9110#section-5.6.2
+pub fn is_tchar(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&byte)
+}
+
+// token = 1*tchar - rfc9110#section-5.6.2
+pub fn is_token(bytes: &[u8]) -> bool {
+    !bytes.is_empty() && bytes.iter().all(|&byte| is_tchar(byte))
+}
+
+// field-value = *field-content, made up of field-vchar (VCHAR / obs-text), SP and HTAB - rfc9110#section-5.5
+// CR, LF, NUL and the other control characters are never allowed
+fn is_field_value(bytes: &[u8]) -> bool {
+    bytes.iter().all(|&byte| byte == b' ' || byte == b'\t' || byte.is_ascii_graphic() || byte >= 0x80)
+}
+
+// Removes leading and trailing OWS (i.e., SP / HTAB) - rfc9110#section-5.6.3
+fn trim_ows(bytes: &[u8]) -> &[u8] {
+    let start: usize = bytes.iter().position(|&byte| byte != b' ' && byte != b'\t').unwrap_or(bytes.len());
+    let end: usize = bytes.iter().rposition(|&byte| byte != b' ' && byte != b'\t').map_or(start, |pos| pos + 1);
+    &bytes[start..end]
+}
 
-    //unused, just here to get rid of warnings
-    let http_response: HttpResponse = construct_http_response(b"200".to_vec(), b"OK".to_vec());
-    println!("LOG (CONSTRUCT_HTTP_REQUEST_FROM_VEC_U8):\n   HttpResponse Constructed:\n      version: {:?}\n      status_code: {:?}\n      reason_phrase: {:?}", http_response.start_line.http_version, http_response.start_line.status_code, http_response.start_line.reason_phrase);
-    for (key, value) in http_response.header_field_lines.iter() {
-        println!("      {:?}: {:?}", key, value);
+// Splits a section of CRLF terminated lines into the lines themselves (without the CRLFs)
+fn split_crlf_lines(section: &[u8]) -> Vec<&[u8]> {
+    let mut lines: Vec<&[u8]> = Vec::new();
+    let mut line_start: usize = 0;
+    while let Some(pos) = section[line_start..].windows(2).position(|window| window == b"\r\n") {
+        lines.push(&section[line_start..line_start + pos]);
+        line_start += pos + 2;
     }
-    println!("      body: {:?}", http_response.body);
+    if line_start < section.len() { lines.push(&section[line_start..]) }
+    lines
+}
+
+// Parses a field line (as used in both the header and the trailer section) into its name and value
+// field-line = field-name ":" OWS field-value OWS - rfc9112#section-5
+pub fn parse_field_line(line: &[u8]) -> Result<(&[u8], &[u8]), HttpRequestError> {
+    let colon_index: usize = match line.iter().position(|&byte| byte == b':') {
+        Some(colon_index) => colon_index,
+        None => return Err(HttpRequestError::InvalidHeader),
+    };
+
+    // field-name = token - rfc9110#section-5.1
+    // "No whitespace is allowed between the field name and colon ... a server MUST reject, with a response status code of 400" - rfc9112#section-5.1
+    let field_name: &[u8] = &line[..colon_index];
+    if !is_token(field_name) { return Err(HttpRequestError::InvalidHeader) }
+
+    let field_value: &[u8] = trim_ows(&line[colon_index + 1..]);
+    if !is_field_value(field_value) { return Err(HttpRequestError::InvalidHeader) }
+
+    Ok((field_name, field_value))
+}
+
+// How the length of a request's message body is determined - rfc9112#section-6.3
+#[derive(Debug, PartialEq)]
+pub enum MessageBodyLength {
+    None,               // "If this is a request message and none of the above are true, then the message body length is zero"
+    ContentLength(u64), // "its decimal value defines the expected message body length in octets"
+    Chunked,            // "If a Transfer-Encoding header field is present and the chunked transfer coding is the final encoding..."
+}
 
-    println!("LOG (CONSTRUCT_HTTP_REQUEST_FROM_VEC_U8):\n   HttpRequest Constructed:\n      method: {:?}\n      uri: {:?}\n      version: {:?}", http_request.start_line.method, http_request.start_line.request_target, http_request.start_line.http_version);
-    for (key, value) in http_request.header_field_lines.iter() {
-        println!("      {:?}: {:?}", key, value);
+// Determines the message body length of an HttpRequest from its Content-Length (and Transfer-Encoding) header fields.
+// Invalid, negative or conflicting lengths are a BadRequest; lengths above max_body_size are ContentTooLarge.
+pub fn message_body_length(http_request: &HttpRequest, max_body_size: u64) -> Result<MessageBodyLength, HttpRequestError> {
+    if let Some(transfer_encoding) = http_request.header_field_lines.get_combined(b"Transfer-Encoding") {
+        // "A server or client that receives an HTTP/1.0 message containing a Transfer-Encoding header field MUST treat the message as if
+        // the framing is faulty" - rfc9112#section-6.1
+        if http_request.start_line.http_version == Version::HTTP_1_0 { return Err(HttpRequestError::BadRequest) }
+        // "A server MAY reject a request that contains both Content-Length and Transfer-Encoding" - rfc9112#section-6.1
+        if http_request.header_field_lines.contains_key(b"Content-Length") { return Err(HttpRequestError::BadRequest) }
+
+        let transfer_codings: Vec<&[u8]> = transfer_encoding.split(|&b| b == b',').map(|coding| coding.trim_ascii()).filter(|coding| !coding.is_empty()).collect();
+        for (i, transfer_coding) in transfer_codings.iter().enumerate() {
+            // "A server that receives a request message with a transfer coding it does not understand SHOULD respond with 501" - rfc9112#section-6.1
+            if !transfer_coding.eq_ignore_ascii_case(b"chunked") { return Err(HttpRequestError::UnsupportedTransferCoding) }
+            // "A sender MUST NOT apply the chunked transfer coding more than once" and it MUST be the final coding - rfc9112#section-6.1
+            if i != transfer_codings.len() - 1 { return Err(HttpRequestError::BadRequest) }
+        }
+        if transfer_codings.is_empty() { return Err(HttpRequestError::BadRequest) }
+        return Ok(MessageBodyLength::Chunked)
+    }
+
+    // "Content-Length = 1*DIGIT" - rfc9110#section-8.6
+    // A list of identical values (e.g., "42, 42", or the same value on several field lines) may be accepted as that single value;
+    // anything else is unrecoverable - rfc9112#section-6.3
+    let mut content_length: Option<u64> = None;
+    if let Some(value) = http_request.header_field_lines.get_combined(b"Content-Length") {
+        for element in value.split(|&b| b == b',') {
+            let element: &[u8] = element.trim_ascii();
+            if element.is_empty() || !element.iter().all(|b| b.is_ascii_digit()) { return Err(HttpRequestError::BadRequest) }
+            let length: u64 = match std::str::from_utf8(element).ok().and_then(|digits| digits.parse::<u64>().ok()) {
+                Some(length) => length,
+                None => return Err(HttpRequestError::BadRequest), // Too large to represent -> send 400
+            };
+            match content_length {
+                Some(previous_length) if previous_length != length => return Err(HttpRequestError::BadRequest), // Conflicting lengths -> send 400
+                _ => content_length = Some(length),
+            }
+        }
+    }
+
+    match content_length {
+        Some(length) if length > max_body_size => {
+            ParserLimits::log_exceeded("max_body_size", max_body_size);
+            Err(HttpRequestError::ContentTooLarge) // Send 413
+        }
+        Some(length) => Ok(MessageBodyLength::ContentLength(length)),
+        None => Ok(MessageBodyLength::None),
     }
-    println!("      body: {:?}", http_request.body);
-    Ok(http_request)
 }
 
-pub fn construct_http_response(status_code: Vec<u8>, reason_phrase: Vec<u8>) -> HttpResponse {
+pub fn construct_http_response(status_code: StatusCode) -> HttpResponse {
     let http_response: HttpResponse = HttpResponse {
         start_line: HttpStatusLine {
-            http_version: b"HTTP/1.1".to_vec(),
-            status_code: status_code,
-            reason_phrase: reason_phrase,
+            http_version: Version::HTTP_1_1,
+            status_code,
         },
-        header_field_lines: std::collections::HashMap::new(),
+        header_field_lines: HeaderMap::new(),
         body: None,
     };
     http_response
 }
 
+// Writes an HttpResponse (status line, header section and the message body's raw bytes) to a writer (e.g., a TcpStream)
+// Date, Server and Content-Length are added unless the response already has them. Nothing is added to an interim (1xx) response,
+// and Content-Length is left out of 204 (No Content) and 304 (Not Modified) responses - rfc9110#section-8.6
+// To answer a HEAD request, set Content-Length to the length of the body a GET would have gotten and leave the body out.
+// A response with "Transfer-Encoding: chunked" has its body (and the body's trailer fields) sent with the chunked transfer coding.
+// Otherwise, a body whose length isn't known up front is delimited by closing the connection - rfc9112#section-6.3, so "Connection: close" is added.
+pub fn write_http_response<W: std::io::Write>(writer: &mut W, http_response: HttpResponse) -> std::io::Result<()> {
+    let status_code: StatusCode = http_response.start_line.status_code;
+    let is_interim: bool = status_code.0 < 200;
+    let body_length: Option<u64> = match &http_response.body {
+        Some(body) => body.length(),
+        None => Some(0),
+    };
+    let is_chunked: bool = is_chunked(&http_response.header_field_lines);
+
+    let mut head: Vec<u8> = format!("{} {} {}\r\n", http_response.start_line.http_version, status_code, status_code.reason_phrase()).into_bytes();
+    let mut write_field_line = |name: &[u8], value: &[u8]| {
+        head.extend_from_slice(name);
+        head.extend_from_slice(b": ");
+        head.extend_from_slice(value);
+        head.extend_from_slice(b"\r\n");
+    };
+    // "An origin server with a clock ... MUST generate a Date header field in all ... responses" (except 1xx and 5xx, where it MAY) - rfc9110#section-6.6.1
+    if !is_interim && !http_response.header_field_lines.contains_key(b"Date") {
+        write_field_line(b"Date", date::format_http_date(std::time::SystemTime::now()).as_bytes());
+    }
+    if !is_interim && !http_response.header_field_lines.contains_key(b"Server") {
+        write_field_line(b"Server", SERVER_NAME.as_bytes());
+    }
+    for (name, value) in http_response.header_field_lines.iter() {
+        write_field_line(name, value);
+    }
+    let may_have_content_length: bool = !is_interim && status_code.0 != 204 && status_code.0 != 304;
+    // "A sender MUST NOT send a Content-Length header field in any message that contains a Transfer-Encoding header field" - rfc9112#section-6.2
+    let has_framing: bool = http_response.header_field_lines.contains_key(b"Content-Length") || http_response.header_field_lines.contains_key(b"Transfer-Encoding");
+    if may_have_content_length && !has_framing {
+        match body_length {
+            Some(body_length) => write_field_line(b"Content-Length", body_length.to_string().as_bytes()),
+            None if !http_response.header_field_lines.contains_key(b"Connection") => write_field_line(b"Connection", b"close"),
+            None => {}
+        }
+    }
+    head.extend_from_slice(b"\r\n");
+
+    writer.write_all(&head)?;
+    match http_response.body {
+        Some(body) if is_chunked => body.write_chunked_to(writer)?,
+        Some(body) => body.write_to(writer)?,
+        None => {}
+    }
+    writer.flush()
+}
+
+// Whether chunked is the final transfer coding in the Transfer-Encoding header field (i.e., whether the body is sent in chunks) - rfc9112#section-6.1
+fn is_chunked(header_field_lines: &HeaderMap) -> bool {
+    match header_field_lines.get_combined(b"Transfer-Encoding") {
+        Some(transfer_encoding) => transfer_encoding.rsplit(|&b| b == b',').next().is_some_and(|coding| coding.trim_ascii().eq_ignore_ascii_case(b"chunked")),
+        None => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_http_request_methods() {
-        //for each method in HTTP_METHODS, assert that it is a valid http method
+        // Whether the bytes are one of the registered methods (as opposed to an extension method or not a method at all)
+        let is_http_request_method = |method: &[u8]| matches!(Method::parse(method), Ok(method) if !matches!(method, Method::Extension(_)));
 
-        for method in HTTP_METHODS.iter() {
-            assert!(is_http_request_method(method));
+        //for each method in HTTP_METHODS, assert that it is a valid http method
+        for method in method::HTTP_METHODS.iter() {
+            assert!(is_http_request_method(method.as_bytes()));
         }
         assert!(!is_http_request_method(b"NONE"));  // Not an HTTP method
         assert!(!is_http_request_method(b"get"));   // Lowercase
@@ -206,6 +457,160 @@ mod tests {
         assert!(!is_http_request_method(b"GeT"));   // Mixed case
         assert!(!is_http_request_method(b"GETPOST")); // Concatenated valid methods
         assert!(!is_http_request_method(&(b"A".repeat(100)))); // Excessively long string
+
+        // Any other token is kept as an extension method, anything else is not a method at all
+        assert_eq!(Method::parse(b"PROPFIND").unwrap(), Method::Extension(b"PROPFIND".to_vec()));
+        assert_eq!(Method::parse(b"get").unwrap(), Method::Extension(b"get".to_vec()));
+        assert!(matches!(Method::parse(b"G@T"), Err(HttpRequestError::BadRequest)));
+        assert!(matches!(Method::parse(b""), Err(HttpRequestError::BadRequest)));
+        assert!(Method::Options.is_supported() && !Method::Trace.is_supported() && !Method::Extension(b"PROPFIND".to_vec()).is_supported());
+
+        assert!(matches!(vec_u8_to_http_request(b"PROPFIND / HTTP/1.1\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::UnsupportedMethod)));
+        assert!(matches!(vec_u8_to_http_request(b"TRACE / HTTP/1.1\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::UnsupportedMethod)));
+        assert!(matches!(vec_u8_to_http_request(b"G@T / HTTP/1.1\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::BadRequest)));
+    }
+
+    #[test]
+    fn test_parse_field_line() {
+        assert_eq!(parse_field_line(b"Host: localhost").unwrap(), (&b"Host"[..], &b"localhost"[..]));
+        assert_eq!(parse_field_line(b"Host:localhost").unwrap(), (&b"Host"[..], &b"localhost"[..]));           // No OWS
+        assert_eq!(parse_field_line(b"Accept: \t*/* \t").unwrap(), (&b"Accept"[..], &b"*/*"[..]));           // Leading and trailing OWS
+        assert_eq!(parse_field_line(b"X-Empty:").unwrap(), (&b"X-Empty"[..], &b""[..]));                      // Empty field value
+        assert_eq!(parse_field_line(b"X-Name: caf\xc3\xa9").unwrap(), (&b"X-Name"[..], &b"caf\xc3\xa9"[..])); // obs-text
+        assert!(matches!(parse_field_line(b"Host : localhost"), Err(HttpRequestError::InvalidHeader)));     // Whitespace before the colon
+        assert!(matches!(parse_field_line(b"Bad Name: value"), Err(HttpRequestError::InvalidHeader)));      // Not a token
+        assert!(matches!(parse_field_line(b": value"), Err(HttpRequestError::InvalidHeader)));              // Empty field name
+        assert!(matches!(parse_field_line(b"Host localhost"), Err(HttpRequestError::InvalidHeader)));       // No colon
+        assert!(matches!(parse_field_line(b"X-Nul: a\x00b"), Err(HttpRequestError::InvalidHeader)));        // Control character
+
+        // obs-fold is replaced with a single SP
+        let http_request: HttpRequest = vec_u8_to_http_request(b"GET / HTTP/1.1\r\nX-Folded: first\r\n \t second\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()).unwrap();
+        assert_eq!(http_request.header_field_lines.get(b"X-Folded"), Some(&b"first second"[..]));
+        assert!(matches!(vec_u8_to_http_request(b"GET / HTTP/1.1\r\n Host: localhost\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::BadRequest)));
+        assert!(vec_u8_to_http_request(b"GET / HTTP/1.1\r\nHost:\r\n\r\n".to_vec(), &ParserLimits::default()).is_ok()); // Nothing but an empty Host
+    }
+
+    #[test]
+    fn test_host_and_effective_request_uri() {
+        assert!(matches!(vec_u8_to_http_request(b"GET / HTTP/1.1\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::BadRequest)));                                  // No Host
+        assert!(matches!(vec_u8_to_http_request(b"GET / HTTP/1.1\r\nHost: a\r\nhost: b\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::BadRequest)));          // Two Hosts
+        assert!(matches!(vec_u8_to_http_request(b"GET / HTTP/1.1\r\nHost: a b\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::BadRequest)));                    // Invalid Host
+        assert!(matches!(vec_u8_to_http_request(b"GET / HTTP/1.1\r\nHost: [::1:8000\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::BadRequest)));              // Invalid IPv6 literal
+
+        let effective_request_uri = |request: &[u8]| vec_u8_to_http_request(request.to_vec(), &ParserLimits::default()).unwrap().effective_request_uri("http", "[::1]:8000");
+        assert_eq!(effective_request_uri(b"GET /where?q=now HTTP/1.1\r\nHost: www.example.org:8080\r\n\r\n"), "http://www.example.org:8080/where?q=now");
+        assert_eq!(effective_request_uri(b"GET /index.html HTTP/1.1\r\nHost: [::1]:8000\r\n\r\n"), "http://[::1]:8000/index.html");
+        assert_eq!(effective_request_uri(b"GET / HTTP/1.1\r\nHost:\r\n\r\n"), "http://[::1]:8000/");
+        assert_eq!(effective_request_uri(b"GET https://example.org/a?b HTTP/1.1\r\nHost: other\r\n\r\n"), "https://example.org/a?b");
+        assert_eq!(effective_request_uri(b"OPTIONS * HTTP/1.1\r\nHost: example.org\r\n\r\n"), "http://example.org");
+    }
+
+    #[test]
+    fn test_http_versions() {
+        let http_request: HttpRequest = vec_u8_to_http_request(b"GET / HTTP/1.0\r\n\r\n".to_vec(), &ParserLimits::default()).unwrap(); // Host is optional in HTTP/1.0
+        assert_eq!(http_request.start_line.http_version, Version::HTTP_1_0);
+        assert_eq!(http_request.host, None);
+        assert!(matches!(vec_u8_to_http_request(b"GET / HTTP/2.0\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::UnsupportedVersion)));
+        assert!(matches!(vec_u8_to_http_request(b"GET / HTTP/3.0\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::UnsupportedVersion)));
+        assert!(matches!(vec_u8_to_http_request(b"GET / HTTP/0.9\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::UnsupportedVersion)));
+        let http_request: HttpRequest = vec_u8_to_http_request(b"GET / HTTP/1.2\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()).unwrap(); // A higher minor version is handled as HTTP/1.1
+        assert_eq!(http_request.start_line.http_version, Version::HTTP_1_1);
+        assert!(matches!(vec_u8_to_http_request(b"GET / HTTP/1.0\r\nHost: a\r\nHost: b\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::BadRequest)));
+        assert!(matches!(vec_u8_to_http_request(b"GET / HTTP/one\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::BadRequest)));
+        assert!(matches!(vec_u8_to_http_request(b"GET / http/1.1\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::BadRequest)));   // HTTP-name is case-sensitive
+        assert!(matches!(vec_u8_to_http_request(b"GET / HTTP/1.10\r\nHost: localhost\r\n\r\n".to_vec(), &ParserLimits::default()), Err(HttpRequestError::BadRequest)));  // Single digits only
+        assert_eq!(Version::parse(b"HTTP/2.0"), Some(Version { major: 2, minor: 0 }));
+        assert_eq!(Version::HTTP_1_1.to_string(), "HTTP/1.1");
+
+        let http_request: HttpRequest = vec_u8_to_http_request(b"POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n".to_vec(), &ParserLimits::default()).unwrap();
+        assert!(matches!(message_body_length(&http_request, 100), Err(HttpRequestError::BadRequest)));
+    }
+
+    #[test]
+    fn test_is_persistent_connection() {
+        let is_persistent_connection = |request: &[u8]| vec_u8_to_http_request(request.to_vec(), &ParserLimits::default()).unwrap().is_persistent_connection();
+        assert!(is_persistent_connection(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n"));
+        assert!(!is_persistent_connection(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: Close\r\n\r\n"));
+        assert!(!is_persistent_connection(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: upgrade, close\r\n\r\n"));
+        assert!(!is_persistent_connection(b"GET / HTTP/1.0\r\n\r\n"));
+        assert!(is_persistent_connection(b"GET / HTTP/1.0\r\nConnection: Keep-Alive\r\n\r\n"));
+    }
+
+    #[test]
+    fn test_message_body_length() {
+        let body_length = |request: &[u8]| message_body_length(&vec_u8_to_http_request(request.to_vec(), &ParserLimits::default()).unwrap(), 100);
+
+        assert_eq!(body_length(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap(), MessageBodyLength::None);
+        assert_eq!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 42\r\n\r\n").unwrap(), MessageBodyLength::ContentLength(42));
+        assert_eq!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\ncontent-length: 42, 42\r\n\r\n").unwrap(), MessageBodyLength::ContentLength(42));
+        assert!(matches!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: -1\r\n\r\n"), Err(HttpRequestError::BadRequest)));
+        assert!(matches!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 4x\r\n\r\n"), Err(HttpRequestError::BadRequest)));
+        assert!(matches!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 42, 43\r\n\r\n"), Err(HttpRequestError::BadRequest)));
+        assert!(matches!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 42\r\nContent-Length: 43\r\n\r\n"), Err(HttpRequestError::BadRequest)));
+        assert!(matches!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 99999999999999999999999\r\n\r\n"), Err(HttpRequestError::BadRequest)));
+        assert!(matches!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 101\r\n\r\n"), Err(HttpRequestError::ContentTooLarge)));
+
+        assert_eq!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\n\r\n").unwrap(), MessageBodyLength::Chunked);
+        assert!(matches!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: gzip, chunked\r\n\r\n"), Err(HttpRequestError::UnsupportedTransferCoding)));
+        assert!(matches!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked, chunked\r\n\r\n"), Err(HttpRequestError::BadRequest)));
+        assert!(matches!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\nTransfer-Encoding: chunked\r\n\r\n"), Err(HttpRequestError::BadRequest)));
+        assert!(matches!(body_length(b"POST / HTTP/1.1\r\nHost: localhost\r\nTransfer-Encoding: chunked\r\nContent-Length: 4\r\n\r\n"), Err(HttpRequestError::BadRequest)));
+    }
+
+    #[test]
+    fn test_parser_limits() {
+        let parser_limits: ParserLimits = ParserLimits { max_request_line_length: 32, max_header_count: 3, max_field_line_length: 32, max_header_section_size: 128, max_body_size: 100 };
+        let parse = |request: &[u8]| vec_u8_to_http_request(request.to_vec(), &parser_limits);
+
+        assert!(parse(b"GET /012345678901234567 HTTP/1.1\r\nHost: localhost\r\n\r\n").is_ok());                                                   // 32 byte request-line
+        assert!(matches!(parse(b"GET /0123456789012345678 HTTP/1.1\r\nHost: localhost\r\n\r\n"), Err(HttpRequestError::UriTooLong)));                // 33 byte request-line
+        assert!(parse(b"GET / HTTP/1.1\r\nHost: localhost\r\nA: 1\r\nB: 2\r\n\r\n").is_ok());                                                      // 3 field lines
+        assert!(matches!(parse(b"GET / HTTP/1.1\r\nHost: localhost\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n"), Err(HttpRequestError::HeaderFieldsTooLarge))); // 4 field lines
+        assert!(parse(b"GET / HTTP/1.1\r\nHost: localhost\r\nA: 1\r\n 2\r\n 3\r\n\r\n").is_ok());                                                 // obs-fold is not a new field line
+        assert!(matches!(parse(b"GET / HTTP/1.1\r\nHost: localhost\r\nCookie: 0123456789012345678901234\r\n\r\n"), Err(HttpRequestError::HeaderFieldsTooLarge)));
+        assert!(matches!(parse(b"GET / HTTP/1.1\r\nHost: localhost\r\nA: 01234567890123456789\r\n 0123456789\r\n\r\n"), Err(HttpRequestError::HeaderFieldsTooLarge)));
+        assert!(matches!(parse(&[b"GET / HTTP/1.1\r\nHost: localhost\r\n".to_vec(), b"A: 1\r\n".repeat(20), b"\r\n".to_vec()].concat()), Err(HttpRequestError::HeaderFieldsTooLarge)));
+
+        let http_request: HttpRequest = parse(b"POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: 101\r\n\r\n").unwrap();
+        assert!(matches!(message_body_length(&http_request, parser_limits.max_body_size), Err(HttpRequestError::ContentTooLarge)));
+    }
+
+    #[test]
+    fn test_write_http_response() {
+        let favicon: Vec<u8> = vec![0x00, 0x00, 0x01, 0x00, 0xff, 0xfe, 0x80, 0x0d, 0x0a]; // Not UTF-8, so it must go out byte for byte
+        let mut http_response: HttpResponse = construct_http_response(StatusCode::OK);
+        http_response.header_field_lines.insert(b"Content-Type", b"image/x-icon");
+        http_response.body = Some(HttpResponseBody::Bytes(favicon.clone()));
+
+        let mut written: Vec<u8> = Vec::new();
+        write_http_response(&mut written, http_response).unwrap();
+        let head_end: usize = written.windows(4).position(|window| window == b"\r\n\r\n").unwrap() + 4;
+        let head: String = String::from_utf8(written[..head_end].to_vec()).unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\nDate: "));
+        assert!(head.ends_with(" GMT\r\nServer: server/0.1.0\r\nContent-Type: image/x-icon\r\nContent-Length: 9\r\n\r\n"));
+        assert_eq!(&written[head_end..], &favicon[..]);
+
+        // A HEAD response keeps the Content-Length it was given, and interim responses get no extra header fields at all
+        let mut http_response: HttpResponse = construct_http_response(StatusCode::OK);
+        http_response.header_field_lines.insert(b"Date", b"Sun, 06 Nov 1994 08:49:37 GMT");
+        http_response.header_field_lines.insert(b"Content-Length", b"1234");
+        let mut written: Vec<u8> = Vec::new();
+        write_http_response(&mut written, http_response).unwrap();
+        assert_eq!(written, b"HTTP/1.1 200 OK\r\nServer: server/0.1.0\r\nDate: Sun, 06 Nov 1994 08:49:37 GMT\r\nContent-Length: 1234\r\n\r\n".to_vec());
+
+        let mut written: Vec<u8> = Vec::new();
+        write_http_response(&mut written, construct_http_response(StatusCode::CONTINUE)).unwrap();
+        assert_eq!(written, b"HTTP/1.1 100 Continue\r\n\r\n".to_vec());
+
+        // A chunked body gets neither a Content-Length nor "Connection: close", since the last-chunk marks its end
+        let mut http_response: HttpResponse = construct_http_response(StatusCode::OK);
+        http_response.header_field_lines.insert(b"Date", b"Sun, 06 Nov 1994 08:49:37 GMT");
+        http_response.header_field_lines.insert(b"Transfer-Encoding", b"chunked");
+        http_response.body = Some(HttpResponseBody::Reader { reader: Box::new(&b"Wikipedia"[..]), length: None });
+        let mut written: Vec<u8> = Vec::new();
+        write_http_response(&mut written, http_response).unwrap();
+        assert_eq!(written, b"HTTP/1.1 200 OK\r\nServer: server/0.1.0\r\nDate: Sun, 06 Nov 1994 08:49:37 GMT\r\nTransfer-Encoding: chunked\r\n\r\n9\r\nWikipedia\r\n0\r\n\r\n".to_vec());
+        assert!(!is_chunked(&HeaderMap::new()));
     }
 
     #[test]
@@ -215,37 +620,42 @@ mod tests {
         // What if we are passed no body?
         // What if we are passed no request line? etc.
 
-        let http_request: HttpRequest = vec_u8_to_http_request(b"GET / HTTP/1.1\r\nHost: localhost:8000\r\nUser-Agent: curl/7.64.1\r\nAccept: */*\r\n\r\n".to_vec()).unwrap();
+        let http_request: HttpRequest = vec_u8_to_http_request(b"GET / HTTP/1.1\r\nHost: localhost:8000\r\nUser-Agent: curl/7.64.1\r\nAccept: */*\r\n\r\n".to_vec(), &ParserLimits::default()).unwrap();
 
         // Construct an HttpRequest to compare against the one returned from vec_u8_to_http_request
         let http_request_line: HttpRequestLine = HttpRequestLine {
-            method: b"GET".to_vec(),
-            request_target: b"/".to_vec(),
-            http_version: b"HTTP/1.1".to_vec(),
+            method: Method::Get,
+            request_target: RequestTarget::Origin { path: b"/".to_vec(), query: None },
+            http_version: Version::HTTP_1_1,
         };
 
-        let mut headers: std::collections::HashMap<Vec<u8>, Vec<u8>> = std::collections::HashMap::new();
-        headers.insert(b"Host".to_vec(), b"localhost:8000".to_vec());
-        headers.insert(b"User-Agent".to_vec(), b"curl/7.64.1".to_vec());
-        headers.insert(b"Accept".to_vec(), b"*/*".to_vec());
+        let mut headers: HeaderMap = HeaderMap::new();
+        headers.append(b"Host", b"localhost:8000");
+        headers.append(b"User-Agent", b"curl/7.64.1");
+        headers.append(b"Accept", b"*/*");
 
         let http_request_to_compare: HttpRequest = HttpRequest {
             start_line: http_request_line,
+            uri: Some(RequestUri { path: String::from("/"), query: Vec::new() }),
+            host: Some(Host { name: String::from("localhost"), port: Some(8000) }),
             header_field_lines: headers,
             body: None,
+            trailer_fields: HeaderMap::new(),
         };
 
         // Compare the two HttpRequests
         assert_eq!(http_request.start_line.method,         http_request_to_compare.start_line.method);
         assert_eq!(http_request.start_line.request_target, http_request_to_compare.start_line.request_target);
         assert_eq!(http_request.start_line.http_version,   http_request_to_compare.start_line.http_version);
+        assert_eq!(http_request.uri,                       http_request_to_compare.uri);
+        assert_eq!(http_request.host,                      http_request_to_compare.host);
 
         for (key, value) in http_request.header_field_lines.iter() {
-            assert_eq!(http_request_to_compare.header_field_lines.contains_key(key), true);
+            assert!(http_request_to_compare.header_field_lines.contains_key(key));
             assert_eq!(http_request_to_compare.header_field_lines.get(key), Some(value));
         }
         for (key, value) in http_request_to_compare.header_field_lines.iter() {
-            assert_eq!(http_request.header_field_lines.contains_key(key), true);
+            assert!(http_request.header_field_lines.contains_key(key));
             assert_eq!(http_request.header_field_lines.get(key), Some(value));
         }
     }
@@ -0,0 +1,265 @@
+// tcp/http/range.rs
+
+// A Range header field asking for more ranges than this is ignored (i.e., the whole representation is sent),
+// since it costs far more to answer than to ask for, and no real client needs that many - rfc9110#section-17.15
+const MAX_RANGES: usize = 100;
+
+// An inclusive range of octets (e.g., "bytes=0-499" is ByteRange { first: 0, last: 499 }) - rfc9110#section-14.1.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub first: u64,
+    pub last: u64,
+}
+
+// How a GET request for a representation of the given length is to be answered
+#[derive(Debug, PartialEq, Eq)]
+pub enum RangeResponse {
+    Full,                    // 200, the whole representation
+    Partial(Vec<ByteRange>), // 206, only the given (sorted, non-overlapping) ranges
+    NotSatisfiable,          // 416, none of the requested ranges overlap the representation
+}
+
+// Decides whether a request gets the whole representation or only part of it - rfc9110#section-14.2
+// Range is ignored for any method but GET, for any unit but bytes, when it is invalid, and when If-Range doesn't match the current representation.
+pub fn evaluate_range(http_request: &super::HttpRequest, validators: &super::Validators, length: u64) -> RangeResponse {
+    // "A server MUST ignore a Range header field received with a request method that is unrecognized or for which range handling is not defined.
+    // For this specification, GET is the only method for which range handling is defined."
+    if http_request.start_line.method != super::Method::Get { return RangeResponse::Full }
+    let Some(range) = http_request.header_field_lines.get_combined(b"Range") else { return RangeResponse::Full };
+    if !super::if_range_passes(http_request, validators) { return RangeResponse::Full }
+    // A zero-length representation has no octets to select, so it is simply sent whole
+    if length == 0 { return RangeResponse::Full }
+
+    let Some(byte_ranges) = parse_range(&range, length) else { return RangeResponse::Full };
+    if byte_ranges.is_empty() { return RangeResponse::NotSatisfiable }
+    RangeResponse::Partial(coalesce(byte_ranges))
+}
+
+// Parses a Range field value into the byte ranges that are satisfiable for a representation of the given length,
+// or returns None if the field value is invalid (or uses a unit other than bytes) and has to be ignored.
+// ranges-specifier = range-unit "=" range-set, range-set = 1#range-spec, range-spec = int-range / suffix-range / other-range - rfc9110#section-14.1.1
+fn parse_range(range: &[u8], length: u64) -> Option<Vec<ByteRange>> {
+    let range: &str = std::str::from_utf8(range).ok()?;
+    let (range_unit, range_set) = range.split_once('=')?;
+    if !range_unit.trim().eq_ignore_ascii_case("bytes") { return None }
+
+    let range_specs: Vec<&str> = range_set.split(',').map(|range_spec| range_spec.trim()).filter(|range_spec| !range_spec.is_empty()).collect();
+    if range_specs.is_empty() || range_specs.len() > MAX_RANGES { return None }
+    let is_digits = |value: &str| !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit());
+
+    let mut byte_ranges: Vec<ByteRange> = Vec::new();
+    for range_spec in range_specs {
+        let (first_pos, last_pos) = range_spec.split_once('-')?;
+        if first_pos.is_empty() {
+            // suffix-range = "-" suffix-length, the last suffix-length octets
+            if !is_digits(last_pos) { return None }
+            let suffix_length: u64 = last_pos.parse().unwrap_or(u64::MAX);
+            // "If the selected representation is shorter than the specified suffix-length, the entire representation is used"
+            if suffix_length > 0 { byte_ranges.push(ByteRange { first: length.saturating_sub(suffix_length), last: length - 1 }) }
+        } else {
+            // int-range = first-pos "-" [ last-pos ]
+            if !is_digits(first_pos) || !(last_pos.is_empty() || is_digits(last_pos)) { return None }
+            let first: u64 = first_pos.parse().unwrap_or(u64::MAX);
+            let last: u64 = if last_pos.is_empty() { u64::MAX } else { last_pos.parse().unwrap_or(u64::MAX) };
+            // "An int-range is invalid if the last-pos value is present and less than the first-pos"
+            if last < first { return None }
+            // "the range is satisfiable if the first-pos is less than the current length of the selected representation", and its last-pos is capped at the end
+            if first < length { byte_ranges.push(ByteRange { first, last: std::cmp::min(last, length - 1) }) }
+        }
+    }
+    Some(byte_ranges)
+}
+
+// Sorts the byte ranges and merges the ones that overlap or are adjacent, so that no octet is sent twice
+// "A server MAY coalesce any of the ranges that overlap, or that are separated by a gap that is smaller than the overhead of sending multiple parts" - rfc9110#section-14.2
+fn coalesce(mut byte_ranges: Vec<ByteRange>) -> Vec<ByteRange> {
+    byte_ranges.sort_by_key(|byte_range| byte_range.first);
+    let mut coalesced: Vec<ByteRange> = Vec::new();
+    for byte_range in byte_ranges {
+        match coalesced.last_mut() {
+            Some(previous) if byte_range.first <= previous.last.saturating_add(1) => previous.last = std::cmp::max(previous.last, byte_range.last),
+            _ => coalesced.push(byte_range),
+        }
+    }
+    coalesced
+}
+
+// Builds the 206 (Partial Content) response for the given byte ranges of a file - rfc9110#section-15.3.7
+// A single range is sent as is with a Content-Range, several ranges as the parts of a multipart/byteranges body - rfc9110#section-14.6
+// Either way the file is streamed, reading only the requested ranges.
+pub fn partial_content_response(file: std::fs::File, length: u64, byte_ranges: &[ByteRange], content_type: &[u8]) -> super::HttpResponse {
+    let mut http_response: super::HttpResponse = super::construct_http_response(super::StatusCode::PARTIAL_CONTENT);
+    let mut segments: std::collections::VecDeque<Segment> = std::collections::VecDeque::new();
+
+    if let [byte_range] = byte_ranges {
+        http_response.header_field_lines.insert(b"Content-Type", content_type);
+        http_response.header_field_lines.insert(b"Content-Range", content_range(byte_range, length));
+        segments.push_back(Segment::File { position: byte_range.first, remaining: byte_range.last - byte_range.first + 1 });
+    } else {
+        let boundary: String = boundary();
+        http_response.header_field_lines.insert(b"Content-Type", format!("multipart/byteranges; boundary={}", boundary));
+        for (index, byte_range) in byte_ranges.iter().enumerate() {
+            let mut part_head: Vec<u8> = Vec::new();
+            if index > 0 { part_head.extend_from_slice(b"\r\n") }
+            part_head.extend_from_slice(format!("--{}\r\nContent-Type: ", boundary).as_bytes());
+            part_head.extend_from_slice(content_type);
+            part_head.extend_from_slice(format!("\r\nContent-Range: {}\r\n\r\n", content_range(byte_range, length)).as_bytes());
+            segments.push_back(Segment::Bytes(std::io::Cursor::new(part_head)));
+            segments.push_back(Segment::File { position: byte_range.first, remaining: byte_range.last - byte_range.first + 1 });
+        }
+        segments.push_back(Segment::Bytes(std::io::Cursor::new(format!("\r\n--{}--\r\n", boundary).into_bytes())));
+    }
+
+    let body_length: u64 = segments.iter().map(|segment| segment.len()).sum();
+    http_response.body = Some(super::HttpResponseBody::Reader { reader: Box::new(ByteRangesReader { file, segments }), length: Some(body_length) });
+    http_response
+}
+
+// Content-Range = range-unit SP ( range-resp / unsatisfied-range ), range-resp = incl-range "/" ( complete-length / "*" ) - rfc9110#section-14.4
+fn content_range(byte_range: &ByteRange, length: u64) -> String {
+    format!("bytes {}-{}/{}", byte_range.first, byte_range.last, length)
+}
+
+// The Content-Range of a 416 (Range Not Satisfiable) response: unsatisfied-range = "*/" complete-length
+pub fn unsatisfied_content_range(length: u64) -> String {
+    format!("bytes */{}", length)
+}
+
+// A boundary that is random for every response, so that it can't be guessed by whoever wrote the file (and appear inside a part) - rfc2046#section-5.1.1
+fn boundary() -> String {
+    let random: u64 = std::hash::BuildHasher::hash_one(&std::collections::hash_map::RandomState::new(), std::time::SystemTime::now());
+    format!("{:016x}", random)
+}
+
+// A piece of a 206 body: either generated bytes (the head of a part) or a range of the file
+enum Segment {
+    Bytes(std::io::Cursor<Vec<u8>>),
+    File { position: u64, remaining: u64 },
+}
+
+impl Segment {
+    fn len(&self) -> u64 {
+        match self {
+            Segment::Bytes(cursor) => cursor.get_ref().len() as u64,
+            Segment::File { remaining, .. } => *remaining,
+        }
+    }
+}
+
+// Reads the segments of a 206 body one after another, seeking to each file range as it is reached
+struct ByteRangesReader {
+    file: std::fs::File,
+    segments: std::collections::VecDeque<Segment>,
+}
+
+impl std::io::Read for ByteRangesReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use std::io::Seek;
+        loop {
+            let Some(segment) = self.segments.front_mut() else { return Ok(0) };
+            let bytes_read: usize = match segment {
+                Segment::Bytes(cursor) => cursor.read(buf)?,
+                Segment::File { remaining: 0, .. } => 0,
+                Segment::File { position, remaining } => {
+                    self.file.seek(std::io::SeekFrom::Start(*position))?;
+                    let to_read: usize = std::cmp::min(*remaining, buf.len() as u64) as usize;
+                    let bytes_read: usize = self.file.read(&mut buf[..to_read])?;
+                    // The file got shorter since its length was taken, so the promised range can't be sent
+                    if bytes_read == 0 && to_read > 0 { return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "the file ended before the requested range")) }
+                    *position += bytes_read as u64;
+                    *remaining -= bytes_read as u64;
+                    bytes_read
+                }
+            };
+            if bytes_read > 0 || buf.is_empty() { return Ok(bytes_read) }
+            self.segments.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{vec_u8_to_http_request, HttpRequest, HttpResponseBody, ParserLimits, Validators};
+
+    #[test]
+    fn test_parse_range() {
+        let byte_range = |first: u64, last: u64| ByteRange { first, last };
+        // The examples from rfc9110#section-14.1.2, for a representation of 10000 octets
+        assert_eq!(parse_range(b"bytes=0-499", 10000), Some(vec![byte_range(0, 499)]));
+        assert_eq!(parse_range(b"bytes=500-999", 10000), Some(vec![byte_range(500, 999)]));
+        assert_eq!(parse_range(b"bytes=-500", 10000), Some(vec![byte_range(9500, 9999)]));
+        assert_eq!(parse_range(b"bytes=9500-", 10000), Some(vec![byte_range(9500, 9999)]));
+        assert_eq!(parse_range(b"bytes=0-0,-1", 10000), Some(vec![byte_range(0, 0), byte_range(9999, 9999)]));
+        assert_eq!(parse_range(b"Bytes= 500-600 , 601-999 ,", 10000), Some(vec![byte_range(500, 600), byte_range(601, 999)]));
+
+        // Ranges past the end are capped or dropped (unsatisfiable), and a suffix longer than the representation selects all of it
+        assert_eq!(parse_range(b"bytes=9000-99999999999999999999", 10000), Some(vec![byte_range(9000, 9999)]));
+        assert_eq!(parse_range(b"bytes=10000-,-0", 10000), Some(Vec::new()));
+        assert_eq!(parse_range(b"bytes=-20000", 10000), Some(vec![byte_range(0, 9999)]));
+
+        // Invalid field values are ignored as a whole
+        assert_eq!(parse_range(b"bytes=500-499", 10000), None);
+        assert_eq!(parse_range(b"bytes=1-2-3", 10000), None);
+        assert_eq!(parse_range(b"bytes=-", 10000), None);
+        assert_eq!(parse_range(b"bytes=", 10000), None);
+        assert_eq!(parse_range(b"bytes=+1-2", 10000), None);
+        assert_eq!(parse_range(b"items=0-5", 10000), None);
+        assert_eq!(parse_range("bytes=0-0,".repeat(MAX_RANGES + 1).as_bytes(), 10000), None);
+
+        assert_eq!(coalesce(vec![byte_range(500, 999), byte_range(0, 99), byte_range(100, 199), byte_range(600, 700)]), vec![byte_range(0, 199), byte_range(500, 999)]);
+    }
+
+    #[test]
+    fn test_evaluate_range() {
+        let validators: Validators = Validators {
+            etag: b"\"abc\"".to_vec(),
+            last_modified: Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(784111777)), // Sun, 06 Nov 1994 08:49:37 GMT
+        };
+        let evaluate = |request_head: &str| {
+            let http_request: HttpRequest = vec_u8_to_http_request(format!("{}\r\nHost: localhost\r\n\r\n", request_head).into_bytes(), &ParserLimits::default()).unwrap();
+            evaluate_range(&http_request, &validators, 100)
+        };
+        let first_ten: RangeResponse = RangeResponse::Partial(vec![ByteRange { first: 0, last: 9 }]);
+
+        assert_eq!(evaluate("GET / HTTP/1.1"), RangeResponse::Full);
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nRange: bytes=0-9"), first_ten);
+        assert_eq!(evaluate("HEAD / HTTP/1.1\r\nRange: bytes=0-9"), RangeResponse::Full);
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nRange: bytes=100-"), RangeResponse::NotSatisfiable);
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nRange: bytes=9-0"), RangeResponse::Full);
+
+        // If-Range only lets the Range through if the representation is still the one the client has part of
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nRange: bytes=0-9\r\nIf-Range: \"abc\""), first_ten);
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nRange: bytes=0-9\r\nIf-Range: W/\"abc\""), RangeResponse::Full);
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nRange: bytes=0-9\r\nIf-Range: \"xyz\""), RangeResponse::Full);
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nRange: bytes=0-9\r\nIf-Range: Sun, 06 Nov 1994 08:49:37 GMT"), first_ten);
+        assert_eq!(evaluate("GET / HTTP/1.1\r\nRange: bytes=0-9\r\nIf-Range: Sun, 06 Nov 1994 08:49:38 GMT"), RangeResponse::Full);
+    }
+
+    #[test]
+    fn test_partial_content_response() {
+        let path: std::path::PathBuf = std::env::temp_dir().join(format!("server_test_partial_content_{}", std::process::id()));
+        std::fs::write(&path, b"0123456789abcdefghij").unwrap();
+        let read_body = |http_response: super::super::HttpResponse| {
+            let mut written: Vec<u8> = Vec::new();
+            let body: HttpResponseBody = http_response.body.unwrap();
+            let length: Option<u64> = body.length();
+            body.write_to(&mut written).unwrap();
+            assert_eq!(length, Some(written.len() as u64)); // The Content-Length is computed up front, so it has to be exact
+            String::from_utf8(written).unwrap()
+        };
+
+        let http_response = partial_content_response(std::fs::File::open(&path).unwrap(), 20, &[ByteRange { first: 5, last: 9 }], b"text/plain");
+        assert_eq!(http_response.header_field_lines.get(b"Content-Range"), Some(&b"bytes 5-9/20"[..]));
+        assert_eq!(read_body(http_response), "56789");
+
+        let http_response = partial_content_response(std::fs::File::open(&path).unwrap(), 20, &[ByteRange { first: 0, last: 1 }, ByteRange { first: 18, last: 19 }], b"text/plain");
+        let content_type: String = String::from_utf8(http_response.header_field_lines.get(b"Content-Type").unwrap().to_vec()).unwrap();
+        let boundary: &str = content_type.strip_prefix("multipart/byteranges; boundary=").unwrap();
+        assert_eq!(
+            read_body(http_response),
+            format!("--{0}\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-1/20\r\n\r\n01\r\n--{0}\r\nContent-Type: text/plain\r\nContent-Range: bytes 18-19/20\r\n\r\nij\r\n--{0}--\r\n", boundary)
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
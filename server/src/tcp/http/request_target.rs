@@ -0,0 +1,239 @@
+// tcp/http/request_target.rs
+
+// request-target = origin-form / absolute-form / authority-form / asterisk-form - rfc9112#section-3.2
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestTarget {
+    // origin-form = absolute-path [ "?" query ], e.g., "/where?q=now" - rfc9112#section-3.2.1
+    Origin { path: Vec<u8>, query: Option<Vec<u8>> },
+    // absolute-form = absolute-URI, e.g., "http://www.example.org/pub/WWW/TheProject.html" - rfc9112#section-3.2.2
+    Absolute { scheme: Vec<u8>, authority: Vec<u8>, path: Vec<u8>, query: Option<Vec<u8>> },
+    // authority-form = uri-host ":" port, e.g., "www.example.com:80" (CONNECT only) - rfc9112#section-3.2.3
+    Authority { host: Vec<u8>, port: u16 },
+    // asterisk-form = "*" (OPTIONS only) - rfc9112#section-3.2.4
+    Asterisk,
+}
+
+impl RequestTarget {
+    // Parses a request-target, using the request method to decide which forms are allowed
+    pub fn parse(method: &super::Method, request_target: &[u8]) -> Result<RequestTarget, super::HttpRequestError> {
+        if request_target.is_empty() { return Err(super::HttpRequestError::BadRequest) }
+
+        // "The asterisk-form of request-target is only used for a server-wide OPTIONS request" - rfc9112#section-3.2.4
+        if request_target == b"*" {
+            if *method == super::Method::Options { return Ok(RequestTarget::Asterisk) }
+            return Err(super::HttpRequestError::BadRequest)
+        }
+
+        // "The authority-form of request-target is only used for CONNECT requests" - rfc9112#section-3.2.3
+        // "When making a CONNECT request ... a client MUST send only the host and port of the tunnel destination as the request-target"
+        if *method == super::Method::Connect {
+            return match parse_authority(request_target) {
+                Some((host, Some(port))) => Ok(RequestTarget::Authority { host: host.to_vec(), port }),
+                _ => Err(super::HttpRequestError::BadRequest),
+            }
+        }
+
+        if request_target[0] == b'/' {
+            let (path, query) = split_path_and_query(request_target)?;
+            return Ok(RequestTarget::Origin { path: path.to_vec(), query: query.map(|query| query.to_vec()) })
+        }
+
+        // absolute-URI = scheme ":" hier-part [ "?" query ], where hier-part = "//" authority path-abempty for http(s) - rfc3986#section-4.3
+        let scheme_end: usize = match request_target.windows(3).position(|window| window == b"://") {
+            Some(scheme_end) => scheme_end,
+            None => return Err(super::HttpRequestError::BadRequest),
+        };
+        let scheme: &[u8] = &request_target[..scheme_end];
+        if !scheme.eq_ignore_ascii_case(b"http") && !scheme.eq_ignore_ascii_case(b"https") { return Err(super::HttpRequestError::BadRequest) }
+
+        let rest: &[u8] = &request_target[scheme_end + 3..];
+        let authority_end: usize = rest.iter().position(|&byte| byte == b'/' || byte == b'?').unwrap_or(rest.len());
+        let authority: &[u8] = &rest[..authority_end];
+        // "A sender MUST NOT generate the userinfo subcomponent (and its "@" delimiter)" - rfc9110#section-4.2.4
+        if parse_authority(authority).is_none() { return Err(super::HttpRequestError::BadRequest) }
+
+        let (path, query) = split_path_and_query(&rest[authority_end..])?;
+        // "If the target URI's path component is empty, the client MUST send "/" as the path" - rfc9112#section-3.2.1
+        let path: Vec<u8> = if path.is_empty() { b"/".to_vec() } else { path.to_vec() };
+        Ok(RequestTarget::Absolute {
+            scheme: scheme.to_ascii_lowercase(),
+            authority: authority.to_vec(),
+            path,
+            query: query.map(|query| query.to_vec()),
+        })
+    }
+
+    // The (still percent-encoded) query of an origin-form or absolute-form request-target, if it has one
+    pub fn query(&self) -> Option<&[u8]> {
+        match self {
+            RequestTarget::Origin { query, .. } | RequestTarget::Absolute { query, .. } => query.as_deref(),
+            RequestTarget::Authority { .. } | RequestTarget::Asterisk => None,
+        }
+    }
+}
+
+// Splits "path?query" into the path and the (optional) query, making sure both only contain characters a URI allows
+fn split_path_and_query(path_and_query: &[u8]) -> Result<(&[u8], Option<&[u8]>), super::HttpRequestError> {
+    let (path, query) = match path_and_query.iter().position(|&byte| byte == b'?') {
+        Some(pos) => (&path_and_query[..pos], Some(&path_and_query[pos + 1..])),
+        None => (path_and_query, None),
+    };
+    // path-abempty = *( "/" segment ), where segment = *pchar - rfc3986#section-3.3
+    if !path.is_empty() && path[0] != b'/' { return Err(super::HttpRequestError::BadRequest) }
+    if !is_uri_component(path, b"/") { return Err(super::HttpRequestError::BadRequest) }
+    // query = *( pchar / "/" / "?" ) - rfc3986#section-3.4
+    if let Some(query) = query {
+        if !is_uri_component(query, b"/?") { return Err(super::HttpRequestError::BadRequest) }
+    }
+    Ok((path, query))
+}
+
+// Checks that every octet is a pchar (or one of the extra allowed octets), with well-formed percent-encodings
+// pchar = unreserved / pct-encoded / sub-delims / ":" / "@" - rfc3986#section-3.3
+fn is_uri_component(component: &[u8], extra_allowed: &[u8]) -> bool {
+    let mut i: usize = 0;
+    while i < component.len() {
+        let byte: u8 = component[i];
+        if byte == b'%' {
+            // pct-encoded = "%" HEXDIG HEXDIG - rfc3986#section-2.1
+            if i + 2 >= component.len() { return false }
+            if !component[i + 1].is_ascii_hexdigit() || !component[i + 2].is_ascii_hexdigit() { return false }
+            i += 3;
+            continue;
+        }
+        if !(is_unreserved(byte) || is_sub_delim(byte) || byte == b':' || byte == b'@' || extra_allowed.contains(&byte)) { return false }
+        i += 1;
+    }
+    true
+}
+
+// unreserved = ALPHA / DIGIT / "-" / "." / "_" / "~" - rfc3986#section-2.3
+fn is_unreserved(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || b"-._~".contains(&byte)
+}
+
+// sub-delims = "!" / "$" / "&" / "'" / "(" / ")" / "*" / "+" / "," / ";" / "=" - rfc3986#section-2.2
+fn is_sub_delim(byte: u8) -> bool {
+    b"!$&'()*+,;=".contains(&byte)
+}
+
+// The value of a Host header field: Host = uri-host [ ":" port ] - rfc9110#section-7.2
+#[derive(Debug, Clone, PartialEq)]
+pub struct Host {
+    pub name: String,       // lowercased, IPv6 literals keep their brackets (e.g., "[::1]")
+    pub port: Option<u16>,
+}
+
+impl Host {
+    // Parses a Host field value. An empty value is valid when the target URI has no authority, so it yields Ok(None).
+    pub fn parse(field_value: &[u8]) -> Result<Option<Host>, super::HttpRequestError> {
+        if field_value.is_empty() { return Ok(None) }
+        match parse_authority(field_value) {
+            Some((name, port)) => Ok(Some(Host { name: String::from_utf8_lossy(name).to_ascii_lowercase(), port })),
+            None => Err(super::HttpRequestError::BadRequest),
+        }
+    }
+}
+
+impl std::fmt::Display for Host {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.port {
+            Some(port) => write!(f, "{}:{}", self.name, port),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+// Parses an authority (without userinfo) into its host and optional port
+// authority = uri-host [ ":" port ], uri-host = IP-literal / IPv4address / reg-name - rfc3986#section-3.2
+pub fn parse_authority(authority: &[u8]) -> Option<(&[u8], Option<u16>)> {
+    let (host, port): (&[u8], Option<&[u8]>) = if authority.starts_with(b"[") {
+        // IP-literal = "[" ( IPv6address / IPvFuture ) "]" - rfc3986#section-3.2.2
+        let close_bracket: usize = authority.iter().position(|&byte| byte == b']')?;
+        let ipv6_address: &str = std::str::from_utf8(&authority[1..close_bracket]).ok()?;
+        ipv6_address.parse::<std::net::Ipv6Addr>().ok()?;
+        match &authority[close_bracket + 1..] {
+            [] => (&authority[..=close_bracket], None),
+            [b':', port @ ..] => (&authority[..=close_bracket], Some(port)),
+            _ => return None,
+        }
+    } else {
+        match authority.iter().rposition(|&byte| byte == b':') {
+            Some(pos) => (&authority[..pos], Some(&authority[pos + 1..])),
+            None => (authority, None),
+        }
+    };
+
+    // reg-name = *( unreserved / pct-encoded / sub-delims ), which also covers IPv4address - rfc3986#section-3.2.2
+    // An http(s) URI "with an empty host identifier" is invalid - rfc9110#section-4.2.1
+    if host.is_empty() { return None }
+    if !host.starts_with(b"[") && !host.iter().all(|&byte| is_unreserved(byte) || is_sub_delim(byte) || byte == b'%') { return None }
+
+    // port = *DIGIT - rfc3986#section-3.2.3
+    let port: Option<u16> = match port {
+        None | Some([]) => None,
+        Some(port) => {
+            if !port.iter().all(|byte| byte.is_ascii_digit()) { return None }
+            Some(std::str::from_utf8(port).ok()?.parse::<u16>().ok()?)
+        }
+    };
+    Some((host, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::Method;
+
+    #[test]
+    fn test_request_target_forms() {
+        assert_eq!(RequestTarget::parse(&Method::Get, b"/where?q=now").unwrap(), RequestTarget::Origin { path: b"/where".to_vec(), query: Some(b"q=now".to_vec()) });
+        assert_eq!(RequestTarget::parse(&Method::Get, b"/my%20page.html").unwrap(), RequestTarget::Origin { path: b"/my%20page.html".to_vec(), query: None });
+        assert_eq!(RequestTarget::parse(&Method::Get, b"http://www.example.org:8080").unwrap(), RequestTarget::Absolute {
+            scheme: b"http".to_vec(), authority: b"www.example.org:8080".to_vec(), path: b"/".to_vec(), query: None,
+        });
+        assert_eq!(RequestTarget::parse(&Method::Get, b"HTTP://[::1]/pub/index.html?x").unwrap(), RequestTarget::Absolute {
+            scheme: b"http".to_vec(), authority: b"[::1]".to_vec(), path: b"/pub/index.html".to_vec(), query: Some(b"x".to_vec()),
+        });
+        assert_eq!(RequestTarget::parse(&Method::Connect, b"www.example.com:443").unwrap(), RequestTarget::Authority { host: b"www.example.com".to_vec(), port: 443 });
+        assert_eq!(RequestTarget::parse(&Method::Options, b"*").unwrap(), RequestTarget::Asterisk);
+
+        assert!(RequestTarget::parse(&Method::Get, b"").is_err());                        // Empty
+        assert!(RequestTarget::parse(&Method::Get, b"*").is_err());                       // asterisk-form is for OPTIONS only
+        assert!(RequestTarget::parse(&Method::Get, b"www.example.com:443").is_err());     // authority-form is for CONNECT only
+        assert!(RequestTarget::parse(&Method::Connect, b"/index.html").is_err());         // CONNECT needs authority-form
+        assert!(RequestTarget::parse(&Method::Connect, b"www.example.com").is_err());     // authority-form needs a port
+        assert!(RequestTarget::parse(&Method::Get, b"/a b").is_err());                     // Invalid character
+        assert!(RequestTarget::parse(&Method::Get, b"/a%2").is_err());                     // Truncated percent-encoding
+        assert!(RequestTarget::parse(&Method::Get, b"/a%zz").is_err());                    // Invalid percent-encoding
+        assert!(RequestTarget::parse(&Method::Get, b"/page#fragment").is_err());           // Fragments are never sent
+        assert!(RequestTarget::parse(&Method::Get, b"ftp://example.org/").is_err());       // Not an http(s) URI
+        assert!(RequestTarget::parse(&Method::Get, b"http://user@example.org/").is_err()); // userinfo
+        assert!(RequestTarget::parse(&Method::Get, b"http:///path").is_err());             // Empty host
+    }
+
+    #[test]
+    fn test_host() {
+        assert_eq!(Host::parse(b"LocalHost:8000").unwrap(), Some(Host { name: String::from("localhost"), port: Some(8000) }));
+        assert_eq!(Host::parse(b"[::1]").unwrap(), Some(Host { name: String::from("[::1]"), port: None }));
+        assert_eq!(Host::parse(b"").unwrap(), None);
+        assert!(Host::parse(b"localhost:http").is_err());
+        assert!(Host::parse(b"a b").is_err());
+        assert_eq!(Host::parse(b"[::1]:8000").unwrap().unwrap().to_string(), "[::1]:8000");
+    }
+
+    #[test]
+    fn test_parse_authority() {
+        assert_eq!(parse_authority(b"localhost:8000"), Some((&b"localhost"[..], Some(8000))));
+        assert_eq!(parse_authority(b"example.org"), Some((&b"example.org"[..], None)));
+        assert_eq!(parse_authority(b"example.org:"), Some((&b"example.org"[..], None)));
+        assert_eq!(parse_authority(b"[::1]:8000"), Some((&b"[::1]"[..], Some(8000))));
+        assert_eq!(parse_authority(b"[2001:db8::7]"), Some((&b"[2001:db8::7]"[..], None)));
+        assert_eq!(parse_authority(b"[::1"), None);            // Unterminated IP-literal
+        assert_eq!(parse_authority(b"[not:an:ip]:80"), None);  // Invalid IPv6 address
+        assert_eq!(parse_authority(b"example.org:99999"), None); // Port out of range
+        assert_eq!(parse_authority(b"example.org:8o"), None);  // Non-numeric port
+        assert_eq!(parse_authority(b"exa mple.org"), None);    // Invalid character
+        assert_eq!(parse_authority(b""), None);                // Empty host
+    }
+}
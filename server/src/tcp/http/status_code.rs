@@ -0,0 +1,107 @@
+// tcp/http/status_code.rs
+
+// status-code = 3DIGIT - rfc9112#section-4
+// "HTTP status codes are extensible" - rfc9110#section-15, so any three digit code can be represented (e.g., StatusCode(299)), registered or not
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct StatusCode(pub u16);
+
+// The canonical reason phrases of every status code defined in rfc9110#section-15 (plus the additional codes of rfc6585)
+const REASON_PHRASES: [(u16, &str); 47] = [
+    (100, "Continue"),
+    (101, "Switching Protocols"),
+    (200, "OK"),
+    (201, "Created"),
+    (202, "Accepted"),
+    (203, "Non-Authoritative Information"),
+    (204, "No Content"),
+    (205, "Reset Content"),
+    (206, "Partial Content"),
+    (300, "Multiple Choices"),
+    (301, "Moved Permanently"),
+    (302, "Found"),
+    (303, "See Other"),
+    (304, "Not Modified"),
+    (305, "Use Proxy"),
+    (307, "Temporary Redirect"),
+    (308, "Permanent Redirect"),
+    (400, "Bad Request"),
+    (401, "Unauthorized"),
+    (402, "Payment Required"),
+    (403, "Forbidden"),
+    (404, "Not Found"),
+    (405, "Method Not Allowed"),
+    (406, "Not Acceptable"),
+    (407, "Proxy Authentication Required"),
+    (408, "Request Timeout"),
+    (409, "Conflict"),
+    (410, "Gone"),
+    (411, "Length Required"),
+    (412, "Precondition Failed"),
+    (413, "Content Too Large"),
+    (414, "URI Too Long"),
+    (415, "Unsupported Media Type"),
+    (416, "Range Not Satisfiable"),
+    (417, "Expectation Failed"),
+    (421, "Misdirected Request"),
+    (422, "Unprocessable Content"),
+    (426, "Upgrade Required"),
+    (428, "Precondition Required"),           // rfc6585#section-3
+    (429, "Too Many Requests"),               // rfc6585#section-4
+    (431, "Request Header Fields Too Large"), // rfc6585#section-5
+    (500, "Internal Server Error"),
+    (501, "Not Implemented"),
+    (502, "Bad Gateway"),
+    (503, "Service Unavailable"),
+    (504, "Gateway Timeout"),
+    (505, "HTTP Version Not Supported"),
+];
+
+impl StatusCode {
+    pub const CONTINUE: StatusCode = StatusCode(100);
+    pub const OK: StatusCode = StatusCode(200);
+    pub const PARTIAL_CONTENT: StatusCode = StatusCode(206);
+    pub const MOVED_PERMANENTLY: StatusCode = StatusCode(301);
+    pub const NOT_MODIFIED: StatusCode = StatusCode(304);
+    pub const BAD_REQUEST: StatusCode = StatusCode(400);
+    pub const FORBIDDEN: StatusCode = StatusCode(403);
+    pub const NOT_FOUND: StatusCode = StatusCode(404);
+    pub const PRECONDITION_FAILED: StatusCode = StatusCode(412);
+    pub const CONTENT_TOO_LARGE: StatusCode = StatusCode(413);
+    pub const URI_TOO_LONG: StatusCode = StatusCode(414);
+    pub const RANGE_NOT_SATISFIABLE: StatusCode = StatusCode(416);
+    pub const EXPECTATION_FAILED: StatusCode = StatusCode(417);
+    pub const REQUEST_HEADER_FIELDS_TOO_LARGE: StatusCode = StatusCode(431);
+    pub const NOT_IMPLEMENTED: StatusCode = StatusCode(501);
+    pub const HTTP_VERSION_NOT_SUPPORTED: StatusCode = StatusCode(505);
+
+    // The canonical reason phrase, or an empty one for an unregistered code
+    // "A client SHOULD ignore the reason-phrase content because it is not a reliable channel for information" - rfc9112#section-4
+    pub fn reason_phrase(&self) -> &'static str {
+        match REASON_PHRASES.iter().find(|(code, _)| *code == self.0) {
+            Some((_, reason_phrase)) => reason_phrase,
+            None => "",
+        }
+    }
+}
+
+impl std::fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reason_phrase() {
+        assert_eq!(StatusCode::OK.reason_phrase(), "OK");
+        assert_eq!(StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE.reason_phrase(), "Request Header Fields Too Large");
+        assert_eq!(StatusCode(422).reason_phrase(), "Unprocessable Content");
+        assert_eq!(StatusCode(504).reason_phrase(), "Gateway Timeout");
+        assert_eq!(StatusCode(299).reason_phrase(), ""); // Unregistered codes are kept, just without a reason phrase
+        assert_eq!(StatusCode(299).to_string(), "299");
+        assert!(REASON_PHRASES.windows(2).all(|pair| pair[0].0 < pair[1].0)); // One entry per code, in order
+    }
+}
@@ -0,0 +1,158 @@
+// tcp/http/uri.rs
+
+// The decoded form of a request-target's path and query, ready for handlers to use
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestUri {
+    pub path: String,                  // percent-decoded, with dot-segments removed, always starting with "/"
+    pub query: Vec<(String, String)>,  // key/value pairs, in the order they were sent
+}
+
+impl RequestUri {
+    // Builds a RequestUri from the (still percent-encoded) path and query of a request-target
+    pub fn parse(path: &[u8], query: Option<&[u8]>) -> Result<RequestUri, super::HttpRequestError> {
+        Ok(RequestUri {
+            path: decode_path(path)?,
+            query: query.map(parse_query).unwrap_or_default(),
+        })
+    }
+
+    // The value of the first query parameter with the given key (e.g., "2" for "v" in "?v=2&v=3")
+    pub fn query_value(&self, key: &str) -> Option<&str> {
+        self.query.iter().find(|(query_key, _)| query_key == key).map(|(_, value)| value.as_str())
+    }
+}
+
+// Decodes every pct-encoded octet ("%" HEXDIG HEXDIG) - rfc3986#section-2.1. Returns None for a malformed percent-encoding.
+pub fn percent_decode(bytes: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded: Vec<u8> = Vec::with_capacity(bytes.len());
+    let mut i: usize = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let high: u8 = hex_value(*bytes.get(i + 1)?)?;
+            let low: u8 = hex_value(*bytes.get(i + 2)?)?;
+            decoded.push(high << 4 | low);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(decoded)
+}
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+// Percent-decodes an absolute-path segment by segment and removes its dot-segments - rfc3986#section-5.2.4
+// A segment that decodes to something containing NUL or "/" (i.e., "%00" or "%2F") is rejected, as is a path that is not UTF-8.
+pub fn decode_path(path: &[u8]) -> Result<String, super::HttpRequestError> {
+    if !path.starts_with(b"/") { return Err(super::HttpRequestError::BadRequest) }
+
+    let segments: Vec<&[u8]> = path[1..].split(|&byte| byte == b'/').collect();
+    let mut output: Vec<Vec<u8>> = Vec::with_capacity(segments.len());
+    for (i, segment) in segments.iter().enumerate() {
+        let segment: Vec<u8> = match percent_decode(segment) {
+            Some(segment) => segment,
+            None => return Err(super::HttpRequestError::BadRequest),
+        };
+        if segment.contains(&0) || segment.contains(&b'/') { return Err(super::HttpRequestError::BadRequest) }
+
+        // A trailing "." or ".." still refers to a directory, so it leaves a trailing "/" behind (e.g., "/a/b/.." -> "/a/")
+        let is_last_segment: bool = i == segments.len() - 1;
+        match segment.as_slice() {
+            b"." => {}
+            b".." => { output.pop(); }
+            _ => { output.push(segment); continue }
+        }
+        if is_last_segment { output.push(Vec::new()) }
+    }
+
+    match String::from_utf8([b"/".to_vec(), output.join(&b'/')].concat()) {
+        Ok(path) => Ok(path),
+        Err(_) => Err(super::HttpRequestError::BadRequest),
+    }
+}
+
+// Percent-encodes a decoded path (e.g., RequestUri::path) so that it can be sent back in a URI (e.g., in a Location header field).
+// Everything but "/" and the characters allowed in a segment is encoded - segment = *pchar, pchar = unreserved / pct-encoded / sub-delims / ":" / "@" - rfc3986#section-3.3
+pub fn encode_path(path: &str) -> String {
+    let mut encoded: String = String::with_capacity(path.len());
+    for &byte in path.as_bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+// Parses an application/x-www-form-urlencoded style query (e.g., "v=2&q=a+b") into ordered key/value pairs
+pub fn parse_query(query: &[u8]) -> Vec<(String, String)> {
+    query.split(|&byte| byte == b'&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value): (&[u8], &[u8]) = match pair.iter().position(|&byte| byte == b'=') {
+                Some(pos) => (&pair[..pos], &pair[pos + 1..]),
+                None => (pair, b""),
+            };
+            (decode_query_component(key), decode_query_component(value))
+        })
+        .collect()
+}
+
+// "+" stands for a space in a query component; anything that does not decode to UTF-8 is replaced rather than rejected
+fn decode_query_component(component: &[u8]) -> String {
+    let component: Vec<u8> = component.iter().map(|&byte| if byte == b'+' { b' ' } else { byte }).collect();
+    match percent_decode(&component) {
+        Some(decoded) => String::from_utf8_lossy(&decoded).into_owned(),
+        None => String::from_utf8_lossy(&component).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_path() {
+        assert_eq!(decode_path(b"/").unwrap(), "/");
+        assert_eq!(decode_path(b"/my%20page.html").unwrap(), "/my page.html");
+        assert_eq!(decode_path(b"/caf%C3%A9/").unwrap(), "/café/");
+        assert_eq!(decode_path(b"/a/../index.html").unwrap(), "/index.html");
+        assert_eq!(decode_path(b"/a/./b/../../c").unwrap(), "/c");
+        assert_eq!(decode_path(b"/a/b/..").unwrap(), "/a/");
+        assert_eq!(decode_path(b"/../../etc/passwd").unwrap(), "/etc/passwd");   // Can never climb above "/"
+        assert_eq!(decode_path(b"/%2e%2e/%2E%2E/secret").unwrap(), "/secret");   // Encoded dot-segments are removed too
+        assert!(decode_path(b"/a%2Fb").is_err());        // Encoded "/" within a segment
+        assert!(decode_path(b"/a%00.html").is_err());    // Encoded NUL
+        assert!(decode_path(b"/%ff").is_err());          // Not UTF-8
+        assert!(decode_path(b"/%zz").is_err());          // Malformed percent-encoding
+
+        assert_eq!(encode_path("/my page/café?#%/"), "/my%20page/caf%C3%A9%3F%23%25/");
+        assert_eq!(decode_path(encode_path("/my page/café?#%/").as_bytes()).unwrap(), "/my page/café?#%/");
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query: Vec<(String, String)> = parse_query(b"v=2&q=a+b%26c&flag&&empty=");
+        assert_eq!(query, vec![
+            (String::from("v"), String::from("2")),
+            (String::from("q"), String::from("a b&c")),
+            (String::from("flag"), String::new()),
+            (String::from("empty"), String::new()),
+        ]);
+
+        let request_uri: RequestUri = RequestUri::parse(b"/index.html", Some(b"v=2&v=3")).unwrap();
+        assert_eq!(request_uri.path, "/index.html");
+        assert_eq!(request_uri.query, vec![(String::from("v"), String::from("2")), (String::from("v"), String::from("3"))]);
+        assert_eq!(request_uri.query_value("v"), Some("2"));
+        assert_eq!(request_uri.query_value("q"), None);
+    }
+}
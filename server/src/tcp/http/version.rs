@@ -0,0 +1,36 @@
+// tcp/http/version.rs
+
+// HTTP-version = HTTP-name "/" DIGIT "." DIGIT - rfc9112#section-2.3
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u8,
+    pub minor: u8,
+}
+
+pub const SUPPORTED_HTTP_VERSIONS: [Version; 2] = [Version::HTTP_1_0, Version::HTTP_1_1];
+
+impl Version {
+    pub const HTTP_1_0: Version = Version { major: 1, minor: 0 };
+    pub const HTTP_1_1: Version = Version { major: 1, minor: 1 };
+
+    // Returns None if the bytes are not an HTTP-version at all (e.g., "HTTP/one" or "http/1.1", HTTP-name is case-sensitive)
+    pub fn parse(http_version: &[u8]) -> Option<Version> {
+        match http_version {
+            [b'H', b'T', b'T', b'P', b'/', major, b'.', minor] if major.is_ascii_digit() && minor.is_ascii_digit() => {
+                Some(Version { major: major - b'0', minor: minor - b'0' })
+            }
+            _ => None,
+        }
+    }
+
+    // Checks if this server supports the HTTP version
+    pub fn is_supported(&self) -> bool {
+        SUPPORTED_HTTP_VERSIONS.contains(self)
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "HTTP/{}.{}", self.major, self.minor)
+    }
+}
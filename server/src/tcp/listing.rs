@@ -0,0 +1,290 @@
+// tcp/listing.rs
+
+// One file or subdirectory of a listed directory
+#[derive(Debug, Clone, PartialEq)]
+struct DirectoryEntry {
+    name: String,
+    is_directory: bool,
+    size: u64, // 0 for a directory
+    modified: Option<std::time::SystemTime>,
+}
+
+// The column a listing is sorted by (?sort=name, ?sort=size or ?sort=modified) and in which order (?order=asc or ?order=desc)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortKey { Name, Size, Modified }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListingFormat { Html, Json }
+
+// Whether config.directory_listings enables listings for the directory at the given request path (e.g., "/artefacts/" also enables "/artefacts/builds/")
+pub fn is_enabled(request_path: &str, config: &super::ServerConfig) -> bool {
+    config.directory_listings.iter().any(|listed_path| request_path.starts_with(listed_path.as_str()))
+}
+
+// Builds the response listing the directory at the given path (which request_path, ending in "/", resolved to).
+// Only the entries that could be served themselves are listed, so hidden files and files behind blocked symbolic links stay hidden.
+// The listing is HTML, or JSON if the client accepts application/json, and is generated as it is sent (see ListingGenerator).
+pub fn listing_response(http_request: &super::http::HttpRequest, request_path: &str, directory: &std::path::Path, config: &super::ServerConfig) -> std::io::Result<super::http::HttpResponse> {
+    let mut entries: Vec<DirectoryEntry> = Vec::new();
+    for dir_entry in std::fs::read_dir(directory)? {
+        let dir_entry: std::fs::DirEntry = dir_entry?;
+        let Ok(name) = dir_entry.file_name().into_string() else { continue }; // a name that isn't UTF-8 can't be requested
+        let Ok(path) = super::resolver::resolve_path(&format!("{}{}", request_path, name), config) else { continue };
+        let Ok(metadata) = std::fs::metadata(&path) else { continue };
+        entries.push(DirectoryEntry {
+            name,
+            is_directory: metadata.is_dir(),
+            size: if metadata.is_dir() { 0 } else { metadata.len() },
+            modified: metadata.modified().ok(),
+        });
+    }
+
+    let query = |key: &str| http_request.uri.as_ref().and_then(|request_uri| request_uri.query_value(key));
+    let sort_key: SortKey = match query("sort") {
+        Some("size") => SortKey::Size,
+        Some("modified") => SortKey::Modified,
+        _ => SortKey::Name,
+    };
+    let descending: bool = query("order") == Some("desc");
+    sort_entries(&mut entries, sort_key, descending);
+
+    let format: ListingFormat = if accepts_json(http_request) { ListingFormat::Json } else { ListingFormat::Html };
+    let mut http_response: super::http::HttpResponse = super::http::construct_http_response(super::http::StatusCode::OK);
+    http_response.header_field_lines.insert(b"Content-Type", match format {
+        ListingFormat::Html => &b"text/html; charset=utf-8"[..],
+        ListingFormat::Json => &b"application/json"[..],
+    });
+    // The same URL gives a different representation depending on the Accept header field - rfc9110#section-12.5.5
+    http_response.header_field_lines.insert(b"Vary", b"Accept");
+    super::insert_nosniff_header(&mut http_response, config);
+    let listing_generator: ListingGenerator = ListingGenerator { request_path: request_path.to_string(), entries: entries.into_iter(), format, sort_key, descending, state: ListingState::Head };
+    http_response.body = Some(super::http::HttpResponseBody::Chunks(Box::new(listing_generator)));
+    if config.compression { super::http::compress_response(http_request, &mut http_response) }
+    Ok(http_response)
+}
+
+// Directories always come first, then the entries are sorted by the key (ties broken by name)
+fn sort_entries(entries: &mut [DirectoryEntry], sort_key: SortKey, descending: bool) {
+    entries.sort_by(|a, b| {
+        let ordering: std::cmp::Ordering = match sort_key {
+            SortKey::Name => std::cmp::Ordering::Equal,
+            SortKey::Size => a.size.cmp(&b.size),
+            SortKey::Modified => a.modified.cmp(&b.modified),
+        }.then_with(|| a.name.cmp(&b.name));
+        b.is_directory.cmp(&a.is_directory).then(if descending { ordering.reverse() } else { ordering })
+    });
+}
+
+// Whether the Accept header field asks for application/json (with a non-zero weight) - rfc9110#section-12.5.1
+// Only an explicit application/json counts, so that "*/*" (e.g., a browser or curl) still gets the HTML listing.
+// A media range with a weight that isn't a qvalue (e.g., "q=nan" or "q=-0") is ignored, as if it wasn't there.
+fn accepts_json(http_request: &super::http::HttpRequest) -> bool {
+    let Some(accept) = http_request.header_field_lines.get_combined(b"Accept") else { return false };
+    String::from_utf8_lossy(&accept).split(',').any(|media_range| {
+        let mut parameters = media_range.split(';').map(|parameter| parameter.trim());
+        let media_type: &str = parameters.next().unwrap_or("");
+        let weight: Option<u16> = match parameters.find_map(|parameter| parameter.split_once('=').filter(|(name, _)| name.trim().eq_ignore_ascii_case("q"))) {
+            Some((_, weight)) => super::http::parse_qvalue(weight.trim()),
+            None => Some(1000),
+        };
+        media_type.eq_ignore_ascii_case("application/json") && weight.is_some_and(|weight| weight > 0)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListingState { Head, Entries, Done }
+
+// Generates a listing one entry at a time, so that a directory with many thousands of files isn't rendered into memory as a whole
+struct ListingGenerator {
+    request_path: String,
+    entries: std::vec::IntoIter<DirectoryEntry>,
+    format: ListingFormat,
+    sort_key: SortKey,
+    descending: bool,
+    state: ListingState,
+}
+
+impl super::http::ChunkGenerator for ListingGenerator {
+    fn next_chunk(&mut self) -> std::io::Result<Option<Vec<u8>>> {
+        let chunk: String = match self.state {
+            ListingState::Head => {
+                self.state = ListingState::Entries;
+                self.head()
+            }
+            ListingState::Entries => match self.entries.next() {
+                Some(entry) => self.entry(&entry, self.entries.len() == 0),
+                None => {
+                    self.state = ListingState::Done;
+                    self.tail()
+                }
+            },
+            ListingState::Done => return Ok(None),
+        };
+        Ok(Some(chunk.into_bytes()))
+    }
+}
+
+impl ListingGenerator {
+    fn head(&self) -> String {
+        match self.format {
+            ListingFormat::Json => format!("{{\"path\":{},\"entries\":[", json_string(&self.request_path)),
+            ListingFormat::Html => {
+                let title: String = html_escape(&format!("Index of {}", self.request_path));
+                // A column header sorts by its column, or reverses the order if the listing is already sorted by it
+                let column = |label: &str, sort_key: SortKey, name: &str| {
+                    let order: &str = if self.sort_key == sort_key && !self.descending { "desc" } else { "asc" };
+                    format!("<th><a href=\"?sort={}&amp;order={}\">{}</a></th>", name, order, label)
+                };
+                let parent: &str = if self.request_path == "/" { "" } else { "  <tr><td><a href=\"../\">../</a></td><td></td><td></td></tr>\n" };
+                format!(
+                    "<!DOCTYPE html>\n<html lang=\"en-US\">\n<head>\n  <meta charset=\"utf-8\">\n  <title>{0}</title>\n</head>\n<body>\n  <h1>{0}</h1>\n  <table>\n  <tr>{1}{2}{3}</tr>\n{4}",
+                    title,
+                    column("Name", SortKey::Name, "name"),
+                    column("Size", SortKey::Size, "size"),
+                    column("Last modified", SortKey::Modified, "modified"),
+                    parent
+                )
+            }
+        }
+    }
+
+    fn entry(&self, entry: &DirectoryEntry, is_last: bool) -> String {
+        let name: String = if entry.is_directory { format!("{}/", entry.name) } else { entry.name.clone() };
+        match self.format {
+            ListingFormat::Json => {
+                let modified: String = entry.modified.and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok()).map_or(String::from("null"), |modified| modified.as_secs().to_string());
+                format!(
+                    "{{\"name\":{},\"type\":\"{}\",\"size\":{},\"modified\":{}}}{}",
+                    json_string(&entry.name),
+                    if entry.is_directory { "directory" } else { "file" },
+                    entry.size,
+                    modified,
+                    if is_last { "" } else { "," }
+                )
+            }
+            ListingFormat::Html => format!(
+                // "./" keeps a name with a ":" in it (e.g., "a:b") from being read as a URI with a scheme - rfc3986#section-4.2
+                "  <tr><td><a href=\"./{}\">{}</a></td><td>{}</td><td>{}</td></tr>\n",
+                html_escape(&super::http::encode_path(&name)),
+                html_escape(&name),
+                if entry.is_directory { String::from("-") } else { entry.size.to_string() },
+                entry.modified.map_or(String::new(), super::http::format_http_date)
+            ),
+        }
+    }
+
+    fn tail(&self) -> String {
+        match self.format {
+            ListingFormat::Json => String::from("]}\n"),
+            ListingFormat::Html => String::from("  </table>\n</body>\n</html>\n"),
+        }
+    }
+}
+
+// Escapes the characters that have a meaning in HTML text and attribute values, so that a file name can't inject markup (e.g., "<script>.txt")
+fn html_escape(text: &str) -> String {
+    let mut escaped: String = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Formats text as a JSON string, quotes included - rfc8259#section-7
+fn json_string(text: &str) -> String {
+    let mut escaped: String = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::http::{vec_u8_to_http_request, ChunkGenerator, HttpRequest, ParserLimits};
+
+    fn entry(name: &str, is_directory: bool, size: u64, modified: u64) -> DirectoryEntry {
+        DirectoryEntry { name: String::from(name), is_directory, size, modified: Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(modified)) }
+    }
+
+    #[test]
+    fn test_sort_entries() {
+        let mut entries: Vec<DirectoryEntry> = vec![entry("b.txt", false, 10, 3), entry("a.txt", false, 20, 1), entry("docs", true, 0, 2)];
+        let names = |entries: &[DirectoryEntry]| entries.iter().map(|entry| entry.name.clone()).collect::<Vec<String>>();
+        sort_entries(&mut entries, SortKey::Name, false);
+        assert_eq!(names(&entries), ["docs", "a.txt", "b.txt"]);
+        sort_entries(&mut entries, SortKey::Size, true);
+        assert_eq!(names(&entries), ["docs", "a.txt", "b.txt"]);
+        sort_entries(&mut entries, SortKey::Modified, false);
+        assert_eq!(names(&entries), ["docs", "a.txt", "b.txt"]);
+        sort_entries(&mut entries, SortKey::Modified, true);
+        assert_eq!(names(&entries), ["docs", "b.txt", "a.txt"]);
+    }
+
+    #[test]
+    fn test_accepts_json() {
+        let accepts = |accept: &str| {
+            let http_request: HttpRequest = vec_u8_to_http_request(format!("GET / HTTP/1.1\r\nHost: localhost\r\nAccept: {}\r\n\r\n", accept).into_bytes(), &ParserLimits::default()).unwrap();
+            accepts_json(&http_request)
+        };
+        assert!(accepts("application/json"));
+        assert!(accepts("text/html;q=0.9, Application/JSON;q=0.5"));
+        assert!(!accepts("*/*"));
+        assert!(!accepts("application/json;q=0"));
+        assert!(!accepts("application/jsonx"));
+        // Weights that aren't qvalues make the media range invalid, so it is ignored rather than read as a refusal (or an acceptance)
+        assert!(!accepts("application/json;q=nan"));
+        assert!(!accepts("application/json;q=1e5"));
+        assert!(accepts("application/json;q=-0, application/json;q=0.001"));
+        assert!(accepts("application/json;Q=1.000"));
+        assert!(!accepts("application/json;q=0.000"));
+    }
+
+    #[test]
+    fn test_listing_generator() {
+        let generate = |format: ListingFormat| {
+            let mut listing_generator: ListingGenerator = ListingGenerator {
+                request_path: String::from("/a&b/"),
+                entries: vec![entry("docs", true, 0, 0), entry("<script>\"x\".txt", false, 5, 784111777)].into_iter(),
+                format,
+                sort_key: SortKey::Name,
+                descending: false,
+                state: ListingState::Head,
+            };
+            let mut listing: Vec<u8> = Vec::new();
+            while let Some(chunk) = listing_generator.next_chunk().unwrap() { listing.extend_from_slice(&chunk) }
+            String::from_utf8(listing).unwrap()
+        };
+
+        assert_eq!(
+            generate(ListingFormat::Json),
+            "{\"path\":\"/a&b/\",\"entries\":[{\"name\":\"docs\",\"type\":\"directory\",\"size\":0,\"modified\":0},{\"name\":\"<script>\\\"x\\\".txt\",\"type\":\"file\",\"size\":5,\"modified\":784111777}]}\n"
+        );
+        let html: String = generate(ListingFormat::Html);
+        assert!(html.contains("<title>Index of /a&amp;b/</title>"));
+        assert!(html.contains("<a href=\"?sort=name&amp;order=desc\">Name</a>"));
+        assert!(html.contains("<tr><td><a href=\"./docs/\">docs/</a></td><td>-</td>"));
+        assert!(html.contains("<a href=\"./%3Cscript%3E%22x%22.txt\">&lt;script&gt;&quot;x&quot;.txt</a></td><td>5</td><td>Sun, 06 Nov 1994 08:49:37 GMT</td>"));
+        assert!(!html.contains("<script>"));
+
+        assert_eq!(json_string("tab\tnul\u{0}"), "\"tab\\tnul\\u0000\"");
+    }
+}
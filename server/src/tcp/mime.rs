@@ -0,0 +1,99 @@
+// tcp/mime.rs
+
+// The media type of a file whose extension is unknown: "arbitrary binary data" - rfc2046#section-4.5.1
+const DEFAULT_MEDIA_TYPE: &str = "application/octet-stream";
+
+// The media types of the file extensions most commonly served by a website
+const DEFAULT_MEDIA_TYPES: [(&str, &str); 34] = [
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("js", "text/javascript"), // rfc9239#section-6
+    ("mjs", "text/javascript"),
+    ("txt", "text/plain"),
+    ("csv", "text/csv"),
+    ("md", "text/markdown"),
+    ("xml", "application/xml"),
+    ("json", "application/json"),
+    ("map", "application/json"),
+    ("webmanifest", "application/manifest+json"),
+    ("wasm", "application/wasm"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("ico", "image/x-icon"),
+    ("bmp", "image/bmp"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("wav", "audio/wav"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+];
+
+// Maps file extensions (case-insensitively) to the media type sent in the Content-Type header field - rfc9110#section-8.3
+pub struct MimeTable {
+    media_types: std::collections::HashMap<String, String>,
+}
+
+impl Default for MimeTable {
+    fn default() -> MimeTable {
+        let mut mime_table: MimeTable = MimeTable { media_types: std::collections::HashMap::new() };
+        for (extension, media_type) in DEFAULT_MEDIA_TYPES {
+            mime_table.insert(extension, media_type);
+        }
+        mime_table
+    }
+}
+
+impl MimeTable {
+    // Adds the media type of an extension (without the leading "."), replacing the built-in one if there is one
+    pub fn insert(&mut self, extension: &str, media_type: &str) {
+        self.media_types.insert(extension.trim_start_matches('.').to_ascii_lowercase(), media_type.trim().to_string());
+    }
+
+    // The Content-Type of the file at the given path, based on its extension
+    // Text types are given a charset, since "text/plain" without one would otherwise be read as US-ASCII - rfc2046#section-4.1.2
+    pub fn content_type(&self, path: &std::path::Path) -> String {
+        let media_type: &str = match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) => self.media_types.get(&extension.to_ascii_lowercase()).map_or(DEFAULT_MEDIA_TYPE, |media_type| media_type.as_str()),
+            None => DEFAULT_MEDIA_TYPE,
+        };
+        if media_type.starts_with("text/") && !media_type.contains(';') { return format!("{}; charset=utf-8", media_type) }
+        media_type.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_type() {
+        let mut mime_table: MimeTable = MimeTable::default();
+        let content_type = |mime_table: &MimeTable, path: &str| mime_table.content_type(std::path::Path::new(path));
+        assert_eq!(content_type(&mime_table, "site/index.html"), "text/html; charset=utf-8");
+        assert_eq!(content_type(&mime_table, "site/styles/style.CSS"), "text/css; charset=utf-8");
+        assert_eq!(content_type(&mime_table, "site/favicon.ico"), "image/x-icon");
+        assert_eq!(content_type(&mime_table, "site/archive.tar.gz"), "application/gzip");
+        assert_eq!(content_type(&mime_table, "site/data.unknown"), "application/octet-stream");
+        assert_eq!(content_type(&mime_table, "site/Makefile"), "application/octet-stream");
+
+        // Overrides replace the built-in media type, and a charset that was configured is kept as is
+        mime_table.insert(".ico", "image/vnd.microsoft.icon");
+        mime_table.insert("TXT", "text/plain; charset=iso-8859-1");
+        assert_eq!(content_type(&mime_table, "site/favicon.ico"), "image/vnd.microsoft.icon");
+        assert_eq!(content_type(&mime_table, "site/notes.txt"), "text/plain; charset=iso-8859-1");
+        assert_eq!(DEFAULT_MEDIA_TYPES.len(), MimeTable::default().media_types.len()); // One entry per extension
+    }
+}
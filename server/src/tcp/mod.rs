@@ -1,148 +1,418 @@
 // tcp/mod.rs
 
-mod auxillary;
+mod config;
+mod error;
+mod error_page;
 mod http; // the reason for the tcp folder: https://doc.rust-lang.org/rust-by-example/mod/split.html
-const SITE_PATH: &str = "site/";
-
-use std::io::{Read, Write};
-
-pub fn handle_tcp_stream(mut tcp_stream: std::net::TcpStream) {
-    let tcp_stream_vec_u8: Vec<u8> = tcp_stream_to_vec_u8(&tcp_stream); // read request into typeless vector
-    
-    // TODO: We prob don't want to assert here. Handle this gracefully instead
-    assert!(auxillary::is_vec_u8_ascii(tcp_stream_vec_u8.clone()), "not valid US-ASCII"); // make sure the typeless vector is valid US-ASCII
-    
-    // the body of the request will always be empty with current implementation
-    let http_request: http::HttpRequest = match http::vec_u8_to_http_request(tcp_stream_vec_u8) {
-        Ok(http_request) => http_request,
-        Err(e) => {
-            let (status_code, reason_phrase) = match e {
-                http::HttpRequestError::UnsupportedMethod => (b"501".to_vec(), b"Unsupported Method".to_vec()),
-                http::HttpRequestError::BadRequest => (b"400".to_vec(), b"Bad Request".to_vec()),
-                _ => (b"404".to_vec(), b"Not Found".to_vec()),
-            };
-            let http_response: http::HttpResponse = http::construct_http_response(status_code, reason_phrase);
-            // TODO: Send the response
-            println!("ERROR (HANDLE_TCP_STREAM): Invalid HTTP Request: {:?}", e);
-            return; //TODO: Should this println? Should this return?
+mod listing;
+mod mime;
+mod reader;
+mod resolver;
+
+pub use config::ServerConfig;
+
+use std::io::Read;
+
+pub fn handle_tcp_stream(tcp_stream: std::net::TcpStream, config: std::sync::Arc<ServerConfig>) {
+    // Set a read timeout so that idle connections get closed. This will help prevent the server from hanging if the client doesn't close the connection
+    // The thread pool has the connection now, so we want to prevent individual threads from hanging
+    match tcp_stream.set_read_timeout(Some(config.idle_timeout)) {
+        Ok(_) => {}
+        Err(e) => println!("WARNING (HANDLE_TCP_STREAM): Failed to set read timeout: {}", e),
+    }
+
+    // Nothing the client sends can take down the thread: every error ends up here and only ends this one connection.
+    // Requests we can't handle are answered with an error response first, I/O errors and timeouts just close the connection.
+    match handle_http_requests(&tcp_stream, &config) {
+        Ok(_) => return,
+        Err(error::ServerError::Parse(e)) => send_http_error_response(&tcp_stream, e, &config),
+        Err(error::ServerError::Timeout) => println!("LOG (HANDLE_TCP_STREAM): TcpStream idle for {:?}. Closing it.", config.idle_timeout),
+        Err(error::ServerError::Io(e)) => println!("ERROR (HANDLE_TCP_STREAM): TcpStream I/O Error. Closing it. Error: {}", e),
+    }
+    shutdown_tcp_stream(&tcp_stream);
+}
+
+// Reads and answers requests until the client closes the connection, a response is sent with "Connection: close" or an error occurs
+fn handle_http_requests(tcp_stream: &std::net::TcpStream, config: &ServerConfig) -> Result<(), error::ServerError> {
+    // Requests are handled one after the other, so pipelined requests get their responses in order. Any bytes read
+    // past the end of one request stay buffered in the HttpStreamReader and become the start of the next one.
+    let mut http_stream_reader: reader::HttpStreamReader<&std::net::TcpStream> = reader::HttpStreamReader::new(tcp_stream);
+    let mut requests_handled: usize = 0;
+    loop {
+        // Read the request's start-line and header section into a "typeless" vector. It's important to handle HTTP
+        // messages at the byte level and not interpret them as Unicode without considering the specific encoding.
+        let tcp_stream_vec_u8: Vec<u8> = match http_stream_reader.read_message_head(&config.parser_limits)? {
+            Some(message_head) => message_head,
+            None => {
+                println!("LOG (HANDLE_HTTP_REQUESTS): TcpStream closed by the client after {} request(s)", requests_handled);
+                return Ok(());
+            }
+        };
+        println!("LOG (HANDLE_HTTP_REQUESTS): Read {} bytes from the TcpStream", tcp_stream_vec_u8.len());
+
+        requests_handled += 1;
+        let is_last_request: bool = requests_handled >= config.max_requests_per_connection;
+        if !handle_http_request(tcp_stream, &mut http_stream_reader, tcp_stream_vec_u8, config, is_last_request)? {
+            println!("LOG (HANDLE_HTTP_REQUESTS): Closing the TcpStream after {} request(s)", requests_handled);
+            shutdown_tcp_stream(tcp_stream);
+            return Ok(());
         }
-    };
+    }
+}
+
+// Handles a single request whose message head has already been read, returning whether the connection should stay open afterwards
+fn handle_http_request(
+    mut tcp_stream: &std::net::TcpStream,
+    http_stream_reader: &mut reader::HttpStreamReader<&std::net::TcpStream>,
+    tcp_stream_vec_u8: Vec<u8>,
+    config: &ServerConfig,
+    is_last_request: bool,
+) -> Result<bool, error::ServerError> {
+    // the message body is not part of tcp_stream_vec_u8, it is read from the stream below
+    let mut http_request: http::HttpRequest = http::vec_u8_to_http_request(tcp_stream_vec_u8, &config.parser_limits)?;
 
-    let file_path: String = if http_request.start_line.request_target.eq(b"/") {
-        String::from("index.html")
-    } else {
-        String::from_utf8(http_request.start_line.request_target).unwrap()
+    // Whether the connection persists depends on the HTTP version and the Connection header field - rfc9112#section-9.3
+    let keep_alive: bool = !is_last_request && http_request.is_persistent_connection();
+
+    // Log the request's effective request URI, using the listener's own address when the client sent an empty Host
+    let default_authority: String = match tcp_stream.local_addr() {
+        Ok(local_addr) => local_addr.to_string(),
+        Err(_) => String::from("localhost"),
     };
+    println!(
+        "LOG (HANDLE_HTTP_REQUEST): {} {}",
+        http_request.start_line.method,
+        http_request.effective_request_uri(&config.scheme, &default_authority)
+    );
+
+    // Work out how the message body is framed before deciding whether we want it at all (e.g., a Content-Length that is too large -> 413)
+    let message_body_length: http::MessageBodyLength = http::message_body_length(&http_request, config.parser_limits.max_body_size)?;
 
-    let (status_code, reason_phrase) = match std::fs::read(format!("{}{}", SITE_PATH, &file_path)) {
-        Ok(_) => (200, "OK"),
-        Err(_) => (404, "Not Found"),
-    }; // generated by Copilot
-
-    // Load the requested page's contents into a "typeless" vector
-    let file_contents: Vec<u8> = match std::fs::read(format!("{}{}", SITE_PATH, &file_path)) {
-        Ok(v) => v,
-        Err(_) => match std::fs::read(format!("{}{}", SITE_PATH, "404.html")) {
-            // this error match was generated by Copilot
-            Ok(v) => v,
-            Err(e) => {
-                println!("Failed to read 404.html: {}", e);
-                Vec::new() // return an empty vector if both reads fail
+    // The client may be waiting for our go-ahead before it sends the body. If the body is rejected it is never read, which leaves the connection unusable.
+    if !answer_expectation(&mut tcp_stream, &http_request, &message_body_length, config)? { return Ok(false) }
+
+    // Read exactly as many octets as the message body length calls for - rfc9112#section-6
+    http_request.body = match message_body_length {
+        http::MessageBodyLength::None => None,
+        http::MessageBodyLength::ContentLength(content_length) => {
+            let mut body: Vec<u8> = Vec::new();
+            let bytes_read: usize = http_stream_reader.take(content_length).read_to_end(&mut body)?;
+            // "If the sender closes the connection ... before the indicated number of octets are received, the recipient MUST consider the message to be incomplete" - rfc9112#section-6.3
+            if bytes_read as u64 != content_length {
+                println!("ERROR (HANDLE_HTTP_REQUEST): Incomplete message body: received {} of {} octets", bytes_read, content_length);
+                return Err(error::ServerError::Io(std::io::Error::from(std::io::ErrorKind::UnexpectedEof)));
+            }
+            Some(body)
+        }
+        http::MessageBodyLength::Chunked => {
+            let mut chunked_decoder: http::ChunkedDecoder<&mut reader::HttpStreamReader<&std::net::TcpStream>> = http::ChunkedDecoder::new(http_stream_reader, config.parser_limits.max_header_section_size);
+            let mut body: Vec<u8> = Vec::new();
+            match (&mut chunked_decoder).take(config.parser_limits.max_body_size.saturating_add(1)).read_to_end(&mut body) {
+                Ok(_) if body.len() as u64 > config.parser_limits.max_body_size => {
+                    http::ParserLimits::log_exceeded("max_body_size", config.parser_limits.max_body_size);
+                    return Err(error::ServerError::Parse(http::HttpRequestError::ContentTooLarge));
+                }
+                Ok(_) => {
+                    http_request.trailer_fields = chunked_decoder.into_trailer_fields();
+                    Some(body)
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                    println!("ERROR (HANDLE_HTTP_REQUEST): Invalid chunked message body: {}", e);
+                    return Err(error::ServerError::Parse(http::HttpRequestError::BadRequest));
+                }
+                Err(e) => return Err(error::ServerError::from(e)),
             }
+        }
+    };
+
+    // OPTIONS (including the server-wide "OPTIONS *") just describes what the server supports - rfc9110#section-9.3.7
+    if http_request.start_line.method == http::Method::Options {
+        let mut http_response: http::HttpResponse = http::construct_http_response(http::StatusCode::OK);
+        let allow: Vec<String> = http::SUPPORTED_HTTP_METHODS.iter().map(|method| method.to_string()).collect();
+        http_response.header_field_lines.insert(b"Allow", allow.join(", "));
+        insert_connection_header(&mut http_response, &http_request, keep_alive);
+        http::write_http_response(&mut tcp_stream, http_response)?;
+        return Ok(keep_alive);
+    }
+
+    // Only the origin-form and absolute-form have a path. The other forms are only allowed for OPTIONS and CONNECT (handled above or rejected as unsupported)
+    let request_uri: &http::RequestUri = match &http_request.uri {
+        Some(request_uri) => request_uri,
+        None => return Err(error::ServerError::Parse(http::HttpRequestError::BadRequest)),
+    };
+
+    // The path is already percent-decoded and free of dot-segments, and the query has been split off of it
+    let file_path: &str = &request_uri.path;
+
+    // Find the requested page under the site root and open it (it is streamed to the client, never read into memory as a whole),
+    // or use the 404 error page if there is no such page (or the 403/404 one of the BlockedPathPolicy if the page isn't to be served).
+    // A directory is served by its index file, once the path ends in "/" so that relative links in the index file resolve inside the directory.
+    // A directory without one is listed instead if config.directory_listings enables it.
+    let resolved_path: Result<std::path::PathBuf, resolver::ResolveError> = match resolver::resolve_path(file_path, config) {
+        Ok(path) if path.is_dir() && file_path.ends_with('/') => match resolver::resolve_index(file_path, config) {
+            Err(resolver::ResolveError::NotFound) if listing::is_enabled(file_path, config) => Ok(path),
+            resolved_index => resolved_index,
+        },
+        Ok(path) if !path.is_dir() && file_path.ends_with('/') => Err(resolver::ResolveError::NotFound), // a file isn't a directory
+        resolved_path => resolved_path,
+    };
+    let mut http_response: http::HttpResponse = match resolved_path {
+        Ok(path) if path.is_dir() && !file_path.ends_with('/') => redirect_response(&format!("{}/", http::encode_path(file_path)), http_request.start_line.request_target.query()),
+        Ok(path) if path.is_dir() => match listing::listing_response(&http_request, file_path, &path, config) {
+            Ok(http_response) => http_response,
+            Err(_) => error_page::error_response(http::StatusCode::NOT_FOUND, config),
+        },
+        Ok(path) => match http::HttpResponseBody::from_path(&path).and_then(|body| static_file_response(&http_request, &path, body, config)) {
+            Ok(http_response) => http_response,
+            Err(_) => error_page::error_response(http::StatusCode::NOT_FOUND, config),
         },
+        Err(resolver::ResolveError::NotFound) => error_page::error_response(http::StatusCode::NOT_FOUND, config),
+        Err(resolver::ResolveError::Blocked) => {
+            println!("LOG (HANDLE_HTTP_REQUEST): Blocked the request for {}", file_path);
+            error_page::error_response(config.blocked_path_policy.status_code(), config)
+        }
     };
 
-    // Pseudocode for needed logic:
-    // now that we have the page or lackthereof that we want to send to them,
-    // construct the http response with the appropriate status code
-    // send the http response
-
-    let http_response: String = format!(
-        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\n\r\n{}",
-        status_code,
-        reason_phrase,
-        file_contents.len(),
-        String::from_utf8_lossy(&file_contents)
-    );
+    // A body of unknown length is sent in chunks to HTTP/1.1 clients - rfc9112#section-7.1
+    // HTTP/1.0 clients don't understand the chunked transfer coding, so for them it can only be delimited by closing the connection - rfc9112#section-6.1
+    let body_size: Option<u64> = http_response.body.as_ref().map_or(Some(0), |body| body.length());
+    let keep_alive: bool = match body_size {
+        Some(_) => keep_alive,
+        None if http_request.start_line.http_version >= http::Version::HTTP_1_1 => {
+            http_response.header_field_lines.insert(b"Transfer-Encoding", b"chunked");
+            keep_alive
+        }
+        None => false,
+    };
+    insert_connection_header(&mut http_response, &http_request, keep_alive);
+
+    if http_request.start_line.method == http::Method::Head {
+        // "The server SHOULD send the same header fields in response to a HEAD request as it would have sent if the request method had been GET" - rfc9110#section-9.3.2
+        // A 304 has no body to describe, so it gets no Content-Length either
+        if let (Some(_), Some(body_size)) = (&http_response.body, body_size) { http_response.header_field_lines.insert(b"Content-Length", body_size.to_string()) }
+        http_response.body = None;
+    }
 
     // Write the HttpResponse to the TcpStream (i.e., connection)
-    match tcp_stream.write(http_response.as_bytes()) {
-        Ok(size) => match tcp_stream.local_addr() {
-            Ok(local_addr) => println!("LOG (HANDLE_TCP_STREAM): TcpStream Write Success: {} (of size {} bytes) written to {}", file_path, size, local_addr),
-            Err(e) => println!("WARNING (HANDLE_TCP_STREAM): Failed to log the local address: {}", e),
-        },
-        Err(e) => println!("ERROR (HANDLE_TCP_STREAM): TcpStream Write Error: {}", e),
+    http::write_http_response(&mut tcp_stream, http_response)?;
+    match tcp_stream.local_addr() {
+        Ok(local_addr) => println!("LOG (HANDLE_HTTP_REQUEST): TcpStream Write Success: {} (with a body of {} bytes) written to {}", file_path, body_size.map_or(String::from("unknown"), |body_size| body_size.to_string()), local_addr),
+        Err(e) => println!("WARNING (HANDLE_HTTP_REQUEST): Failed to log the local address: {}", e),
     };
+    Ok(keep_alive)
 }
 
-/// returns a "typeless" vector containing the contents of the given TcpStream (i.e., connection)
-///
-/// it's important to handle HTTP messages at the byte level and not interpret them
-/// as Unicode without considering the specific encoding. In Rust, you can handle
-/// HTTP messages at the byte level by reading them into a Vec<u8>.
-///
-/// # Arguments
-///
-/// * `tcp_stream` - The TcpStream to read the contents of
-///
-/// # Returns
-///
-/// A Vec<u8> of the TcpStream contents
-///
-/// # Examples
-///
-/// ```
-/// let tcp_stream_vec_u8: Vec<u8> = tcp_stream_to_vec_u8(&tcp_stream);
-/// ```
-pub fn tcp_stream_to_vec_u8(mut tcp_stream: &std::net::TcpStream) -> Vec<u8> {
-    let mut vec_buffer: Vec<u8> = Vec::new();
-
-    // Set a read timeout for 5 seconds. This will help prevent the server from hanging if the client doesn't close the connection
-    // SHOULD THIS LIVE HERE? OR SHOULD IT LIVE IN THE MAIN FUNCTION? OR EVEN UP ABOVE (IN HANDLETCPSTREAM)?
-    // THE ASSUMPTION IS THAT THE THREAD POOL HAS THE CONNECTION NOW, SO WE WANT TO PREVENT INDIVIDUAL THREADS FROM HANGING
-    match tcp_stream.set_read_timeout(Some(std::time::Duration::new(5, 0))) {
-        Ok(_) => {}
-        Err(e) => println!(
-            "WARNING (TCP_STREAM_TO_VEC_U8): Failed to set read timeout: {}",
-            e
-        ),
+// Expect: 100-continue - rfc9110#section-10.1.1
+// The client is waiting for our go-ahead before it sends the body, so answer with either 100 (Continue) or a final status now.
+// Returns whether the body may be read, or false if a final response (with "Connection: close") has been written instead.
+fn answer_expectation<W: std::io::Write>(writer: &mut W, http_request: &http::HttpRequest, message_body_length: &http::MessageBodyLength, config: &ServerConfig) -> Result<bool, error::ServerError> {
+    let Some(expect) = http_request.header_field_lines.get_combined(b"Expect") else { return Ok(true) };
+    // "A server that receives a 100-continue expectation in an HTTP/1.0 request MUST ignore that expectation"
+    if http_request.start_line.http_version == http::Version::HTTP_1_0 { return Ok(true) }
+    // "A server that receives an Expect field value containing a member other than 100-continue MAY respond with a 417 (Expectation Failed)"
+    if !expect.split(|&b| b == b',').all(|expectation| expectation.trim_ascii().eq_ignore_ascii_case(b"100-continue")) {
+        println!("ERROR (ANSWER_EXPECTATION): Unsupported expectation: {}", String::from_utf8_lossy(&expect));
+        return Err(error::ServerError::Parse(http::HttpRequestError::ExpectationFailed));
     }
+    if *message_body_length == http::MessageBodyLength::None { return Ok(true) }
 
-    // CHANGE THE BELOW TO BUFREADER
-
-    // Read incoming data into a "typeless" vector
-    let mut buffer: [u8; 1024] = [0; 1024]; // Adjust chunk size as needed
-    match tcp_stream.read(&mut buffer) {
-        Ok(0) => {} // Connection closed by the client
-        Ok(bytes_read) => {
-            println!(
-                "LOG (TCP_STREAM_TO_VEC_U8): Read {} bytes from the TcpStream",
-                bytes_read
-            );
-            vec_buffer.extend_from_slice(&buffer[..bytes_read]);
-        }
-        Err(e) => {
-            // Shutdown the current TcpStream (i.e., connection)
-            match tcp_stream.shutdown(std::net::Shutdown::Both) {
-                Ok(_) => {}
-                Err(e) => println!(
-                    "ERROR (TCP_STREAM_TO_VEC_U8): TcpStream Shutdown Error: {}",
-                    e
-                ),
-            };
-
-            // Panic the current thread
-            panic!(
-                "ERROR (TCP_STREAM_TO_VEC_U8): Invalid HTTP Request. Shutting down the TcpStream. Error: {}",
-                e
-            );
+    // Give the hook a chance to reject the body without ever receiving it. The unread body leaves the connection unusable, so close it.
+    if let Some(mut http_response) = (config.expect_continue_hook)(http_request) {
+        println!("LOG (ANSWER_EXPECTATION): Expectation rejected with {}", http_response.start_line.status_code);
+        http_response.header_field_lines.insert(b"Connection", b"close");
+        http::write_http_response(writer, http_response)?;
+        return Ok(false);
+    }
+    // "a server that sends a 100 (Continue) response MUST ultimately send a final status code, once it receives and processes the request content"
+    http::write_http_response(writer, http::construct_http_response(http::StatusCode::CONTINUE))?;
+    Ok(true)
+}
+
+// Builds a 301 (Moved Permanently) response to the given (percent-encoded) path, keeping the query of the request - rfc9110#section-15.4.2
+fn redirect_response(location_path: &str, query: Option<&[u8]>) -> http::HttpResponse {
+    let mut location: Vec<u8> = location_path.as_bytes().to_vec();
+    if let Some(query) = query {
+        location.push(b'?');
+        location.extend_from_slice(query);
+    }
+    let mut http_response: http::HttpResponse = http::construct_http_response(http::StatusCode::MOVED_PERMANENTLY);
+    http_response.header_field_lines.insert(b"Location", location);
+    http_response
+}
+
+// Builds the response for a page that exists, from the conditional and Range header fields of the request:
+//   - 304 (Not Modified) if the client already has the current version of the page, 412 (Precondition Failed) if a precondition fails - rfc9110#section-13.2.2
+//   - 206 (Partial Content) with only the requested ranges, or 416 (Range Not Satisfiable) if none of them are in the page - rfc9110#section-14.2
+//   - 200 (OK) with the whole page otherwise, compressed if the client accepts it (in which case a 304 gets the same weak ETag and Vary)
+fn static_file_response(http_request: &http::HttpRequest, path: &std::path::Path, body: http::HttpResponseBody, config: &ServerConfig) -> std::io::Result<http::HttpResponse> {
+    let http::HttpResponseBody::File { file, length } = body else { return Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, "not a file")) };
+    let validators: http::Validators = http::Validators::from_metadata(&file.metadata()?);
+    let content_type: String = config.mime_table.content_type(path);
+    // Negotiated up front, since a 304 has to describe the same (compressed or not) representation as the 200 would,
+    // and the preconditions are evaluated against the ETag that is sent with it (a weak one if it is compressed)
+    let compression: http::Compression = match config.compression {
+        true => http::negotiate_compression(http_request, content_type.as_bytes(), Some(length)),
+        false => http::Compression::Never,
+    };
+    let weak_validators: Option<http::Validators> = matches!(compression, http::Compression::Encoded(_)).then(|| validators.weakened());
+
+    let mut http_response: http::HttpResponse = match http::evaluate_preconditions(http_request, weak_validators.as_ref().unwrap_or(&validators)) {
+        Some(http::StatusCode::NOT_MODIFIED) => http::construct_http_response(http::StatusCode::NOT_MODIFIED),
+        Some(status_code) => {
+            let mut http_response: http::HttpResponse = error_page::error_response(status_code, config);
+            http::apply_compression(compression, &mut http_response); // not compressed, but it varies by Accept-Encoding all the same
+            return Ok(http_response);
         }
+        // A 206 is always of the uncompressed file, so If-Range is evaluated against the file's own strong ETag
+        None => match http::evaluate_range(http_request, &validators, length) {
+            http::RangeResponse::Full => {
+                let mut http_response: http::HttpResponse = http::construct_http_response(http::StatusCode::OK);
+                http_response.header_field_lines.insert(b"Content-Type", content_type);
+                http_response.body = Some(http::HttpResponseBody::File { file, length });
+                http_response
+            }
+            http::RangeResponse::Partial(byte_ranges) => http::partial_content_response(file, length, &byte_ranges, content_type.as_bytes()),
+            http::RangeResponse::NotSatisfiable => {
+                let mut http_response: http::HttpResponse = error_page::error_response(http::StatusCode::RANGE_NOT_SATISFIABLE, config);
+                http_response.header_field_lines.insert(b"Content-Range", http::unsatisfied_content_range(length));
+                http_response
+            }
+        },
+    };
+    validators.insert_into(&mut http_response.header_field_lines);
+    http_response.header_field_lines.insert(b"Accept-Ranges", b"bytes"); // rfc9110#section-14.3
+    http::apply_compression(compression, &mut http_response);
+    insert_nosniff_header(&mut http_response, config);
+    Ok(http_response)
+}
+
+// Tells the client whether the connection will be kept open after this response - rfc9112#section-9.6
+// HTTP/1.1 connections are persistent unless "close" is sent, HTTP/1.0 ones only if "keep-alive" is sent back
+fn insert_connection_header(http_response: &mut http::HttpResponse, http_request: &http::HttpRequest, keep_alive: bool) {
+    if !keep_alive {
+        http_response.header_field_lines.insert(b"Connection", b"close");
+    } else if http_request.start_line.http_version == http::Version::HTTP_1_0 {
+        http_response.header_field_lines.insert(b"Connection", b"keep-alive");
+    }
+}
+
+// Tells browsers to use the Content-Type as is, instead of sniffing the content for a different one, if the ServerConfig asks for it
+fn insert_nosniff_header(http_response: &mut http::HttpResponse, config: &ServerConfig) {
+    if config.nosniff { http_response.header_field_lines.insert(b"X-Content-Type-Options", b"nosniff") }
+}
+
+// Sends the error response (and error page) for the given HttpRequestError. The connection is not reused afterwards.
+fn send_http_error_response(mut tcp_stream: &std::net::TcpStream, http_request_error: http::HttpRequestError, config: &ServerConfig) {
+    let status_code: http::StatusCode = match http_request_error {
+        http::HttpRequestError::UnsupportedMethod => http::StatusCode::NOT_IMPLEMENTED,
+        http::HttpRequestError::BadRequest => http::StatusCode::BAD_REQUEST,
+        http::HttpRequestError::InvalidHeader => http::StatusCode::BAD_REQUEST,
+        http::HttpRequestError::ContentTooLarge => http::StatusCode::CONTENT_TOO_LARGE,
+        http::HttpRequestError::UnsupportedTransferCoding => http::StatusCode::NOT_IMPLEMENTED,
+        http::HttpRequestError::UnsupportedVersion => http::StatusCode::HTTP_VERSION_NOT_SUPPORTED,
+        http::HttpRequestError::ExpectationFailed => http::StatusCode::EXPECTATION_FAILED,
+        http::HttpRequestError::UriTooLong => http::StatusCode::URI_TOO_LONG,
+        http::HttpRequestError::HeaderFieldsTooLarge => http::StatusCode::REQUEST_HEADER_FIELDS_TOO_LARGE,
+    };
+    // Logged separately from I/O errors, so a malformed request can be told apart from a network failure
+    println!("LOG (SEND_HTTP_ERROR_RESPONSE): Rejecting the request ({:?}) with {} {}", http_request_error, status_code, status_code.reason_phrase());
+    let mut http_response: http::HttpResponse = error_page::error_response(status_code, config);
+    http_response.header_field_lines.insert(b"Connection", b"close");
+    match http::write_http_response(&mut tcp_stream, http_response) {
+        Ok(_) => {}
+        Err(e) => println!("ERROR (SEND_HTTP_ERROR_RESPONSE): TcpStream Write Error: {}", e),
+    };
+}
+
+// Closes our side of the TcpStream (i.e., connection). Anything already written is still delivered.
+fn shutdown_tcp_stream(tcp_stream: &std::net::TcpStream) {
+    match tcp_stream.shutdown(std::net::Shutdown::Both) {
+        Ok(_) => {}
+        Err(e) => println!("ERROR (SHUTDOWN_TCP_STREAM): TcpStream Shutdown Error: {}", e),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_answer_expectation() {
+        let parse = |request: &[u8]| http::vec_u8_to_http_request(request.to_vec(), &http::ParserLimits::default()).unwrap();
+        let config: ServerConfig = ServerConfig::default();
+        let mut written: Vec<u8> = Vec::new();
+
+        // No Expect, an HTTP/1.0 request, or no body to wait for: nothing is written and the body (if any) is read
+        assert!(answer_expectation(&mut written, &parse(b"POST / HTTP/1.1\r\nHost: a\r\nContent-Length: 3\r\n\r\n"), &http::MessageBodyLength::ContentLength(3), &config).unwrap());
+        assert!(answer_expectation(&mut written, &parse(b"POST / HTTP/1.0\r\nExpect: 100-continue\r\nContent-Length: 3\r\n\r\n"), &http::MessageBodyLength::ContentLength(3), &config).unwrap());
+        assert!(answer_expectation(&mut written, &parse(b"GET / HTTP/1.1\r\nHost: a\r\nExpect: 100-continue\r\n\r\n"), &http::MessageBodyLength::None, &config).unwrap());
+        assert!(written.is_empty());
+
+        // Any other expectation -> 417
+        let http_request: http::HttpRequest = parse(b"POST / HTTP/1.1\r\nHost: a\r\nExpect: 100-continue, fly\r\nContent-Length: 3\r\n\r\n");
+        let result: Result<bool, error::ServerError> = answer_expectation(&mut written, &http_request, &http::MessageBodyLength::ContentLength(3), &config);
+        assert!(matches!(result, Err(error::ServerError::Parse(http::HttpRequestError::ExpectationFailed))));
+        assert!(written.is_empty());
+
+        // Accepted -> 100 (Continue) is written before the body is read
+        let http_request: http::HttpRequest = parse(b"POST / HTTP/1.1\r\nHost: a\r\nExpect: 100-Continue\r\nContent-Length: 3\r\n\r\n");
+        assert!(answer_expectation(&mut written, &http_request, &http::MessageBodyLength::ContentLength(3), &config).unwrap());
+        assert_eq!(written, b"HTTP/1.1 100 Continue\r\n\r\n");
+
+        // Rejected by the hook -> its response is written with "Connection: close" and the body is never read
+        let config: ServerConfig = ServerConfig { expect_continue_hook: |_| Some(http::construct_http_response(http::StatusCode::CONTENT_TOO_LARGE)), ..ServerConfig::default() };
+        let mut written: Vec<u8> = Vec::new();
+        assert!(!answer_expectation(&mut written, &http_request, &http::MessageBodyLength::ContentLength(3), &config).unwrap());
+        let written: String = String::from_utf8(written).unwrap();
+        assert!(written.starts_with("HTTP/1.1 413 Content Too Large\r\n"));
+        assert!(written.contains("\r\nConnection: close\r\n"));
     }
-    // println!(
-    //     "Received {} bytes: {}",
-    //     vec_buffer.len(),
-    //     String::from_utf8_lossy(&vec_buffer)
-    // );
 
-    vec_buffer
+    #[test]
+    fn test_static_file_response_compression() {
+        let temp_dir: std::path::PathBuf = std::env::temp_dir().join(format!("server_test_static_file_response_{}", std::process::id()));
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        let path: std::path::PathBuf = temp_dir.join("index.html");
+        std::fs::write(&path, "<p>compressible</p>\n".repeat(100)).unwrap();
+        let config: ServerConfig = ServerConfig::default();
+        let respond = |request: String| {
+            let http_request: http::HttpRequest = http::vec_u8_to_http_request(request.into_bytes(), &http::ParserLimits::default()).unwrap();
+            static_file_response(&http_request, &path, http::HttpResponseBody::from_path(&path).unwrap(), &config).unwrap()
+        };
+
+        let http_response: http::HttpResponse = respond(String::from("GET / HTTP/1.1\r\nHost: a\r\nAccept-Encoding: gzip\r\n\r\n"));
+        assert_eq!(http_response.start_line.status_code, http::StatusCode::OK);
+        assert_eq!(http_response.header_field_lines.get(b"Content-Encoding"), Some(&b"gzip"[..]));
+        let etag: Vec<u8> = http_response.header_field_lines.get(b"ETag").unwrap().to_vec();
+        assert!(etag.starts_with(b"W/\""));
+
+        // Revalidating the compressed page gets a 304 with the same weak ETag and Vary as the 200
+        let http_response: http::HttpResponse = respond(format!("GET / HTTP/1.1\r\nHost: a\r\nAccept-Encoding: gzip\r\nIf-None-Match: {}\r\n\r\n", String::from_utf8_lossy(&etag)));
+        assert_eq!(http_response.start_line.status_code, http::StatusCode::NOT_MODIFIED);
+        assert_eq!(http_response.header_field_lines.get(b"ETag"), Some(&etag[..]));
+        assert_eq!(http_response.header_field_lines.get(b"Vary"), Some(&b"Accept-Encoding"[..]));
+        assert!(http_response.body.is_none());
+
+        // If-Match is compared against the weak ETag that was sent with the compressed page
+        let http_response: http::HttpResponse = respond(format!("GET / HTTP/1.1\r\nHost: a\r\nAccept-Encoding: gzip\r\nIf-Match: {}\r\n\r\n", String::from_utf8_lossy(&etag)));
+        assert_eq!(http_response.start_line.status_code, http::StatusCode::OK);
+        let http_response: http::HttpResponse = respond(format!("GET / HTTP/1.1\r\nHost: a\r\nAccept-Encoding: gzip\r\nIf-Match: {}\r\n\r\n", String::from_utf8_lossy(&etag[2..])));
+        assert_eq!(http_response.start_line.status_code, http::StatusCode::PRECONDITION_FAILED);
+        assert_eq!(http_response.header_field_lines.get(b"Vary"), Some(&b"Accept-Encoding"[..]));
+
+        // A range of the page is sent uncompressed, with the strong ETag, but still varies by Accept-Encoding
+        let http_response: http::HttpResponse = respond(String::from("GET / HTTP/1.1\r\nHost: a\r\nAccept-Encoding: gzip\r\nRange: bytes=0-9\r\n\r\n"));
+        assert_eq!(http_response.start_line.status_code, http::StatusCode::PARTIAL_CONTENT);
+        assert_eq!(http_response.header_field_lines.get(b"ETag"), Some(&etag[2..]));
+        assert_eq!(http_response.header_field_lines.get(b"Vary"), Some(&b"Accept-Encoding"[..]));
+        assert!(!http_response.header_field_lines.contains_key(b"Content-Encoding"));
+        assert_eq!(http_response.body.as_ref().and_then(|body| body.length()), Some(10));
+
+        // A client that doesn't accept gzip revalidates the uncompressed page, which has the strong ETag
+        let http_response: http::HttpResponse = respond(format!("GET / HTTP/1.1\r\nHost: a\r\nIf-None-Match: {}\r\n\r\n", String::from_utf8_lossy(&etag[2..])));
+        assert_eq!(http_response.start_line.status_code, http::StatusCode::NOT_MODIFIED);
+        assert_eq!(http_response.header_field_lines.get(b"ETag"), Some(&etag[2..]));
+        assert_eq!(http_response.header_field_lines.get(b"Vary"), Some(&b"Accept-Encoding"[..]));
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
 }
@@ -0,0 +1,182 @@
+// tcp/reader.rs
+
+// The size of each individual read from the underlying stream. The buffer itself grows as needed.
+const READ_CHUNK_SIZE: usize = 4096;
+
+#[derive(Debug)]
+pub enum StreamReadError {
+    Io(std::io::Error),     // The underlying read failed (this includes read timeouts)
+    UnexpectedEof,          // The peer closed the connection part way through a message head
+    RequestLineTooLong,     // No CRLF was found within the allowed request-line length
+    HeaderSectionTooLarge,  // No CRLF CRLF was found within the allowed number of bytes
+}
+
+impl std::fmt::Display for StreamReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StreamReadError::Io(e) => write!(f, "I/O error: {}", e),
+            StreamReadError::UnexpectedEof => write!(f, "connection closed before the end of the header section"),
+            StreamReadError::RequestLineTooLong => write!(f, "request-line exceeds the maximum allowed length"),
+            StreamReadError::HeaderSectionTooLarge => write!(f, "header section exceeds the maximum allowed size"),
+        }
+    }
+}
+
+/// Incrementally reads HTTP messages from a stream (e.g., a TcpStream).
+///
+/// Bytes are read into a growable buffer until the end of the header section (CRLF CRLF) is seen.
+/// Anything read past the header section stays in the buffer and is handed out first by the
+/// `std::io::Read` implementation, so whatever reads the message body gets every octet in order.
+pub struct HttpStreamReader<R: std::io::Read> {
+    stream: R,
+    buffer: Vec<u8>, // bytes that have been read from the stream but not yet consumed
+}
+
+impl<R: std::io::Read> HttpStreamReader<R> {
+    pub fn new(stream: R) -> HttpStreamReader<R> {
+        HttpStreamReader { stream, buffer: Vec::new() }
+    }
+
+    /// returns the next message head (start-line + header section, including the final CRLF CRLF)
+    ///
+    /// Returns `Ok(None)` if the stream is closed before any bytes of a new message arrive.
+    ///
+    /// # Arguments
+    ///
+    /// * `parser_limits` - The maximum request-line length and the maximum number of bytes the message head may occupy
+    pub fn read_message_head(&mut self, parser_limits: &super::http::ParserLimits) -> Result<Option<Vec<u8>>, StreamReadError> {
+        let max_header_section_size: usize = parser_limits.max_header_section_size;
+        let mut searched_up_to: usize = 0; // where the CRLF CRLF search should resume from
+        loop {
+            // "a server that is expecting to receive and parse a request-line SHOULD ignore at least one empty line (CRLF) received prior to the request-line" - rfc9112#section-2.2
+            while self.buffer.starts_with(b"\r\n") {
+                self.buffer.drain(..2);
+                searched_up_to = 0;
+            }
+
+            let search_start: usize = searched_up_to.saturating_sub(3); // the terminator may straddle two reads
+            if let Some(pos) = self.buffer[search_start..].windows(4).position(|window| window == b"\r\n\r\n") {
+                let head_end: usize = search_start + pos + 4;
+                if head_end > max_header_section_size { return Err(header_section_too_large(parser_limits)) }
+                let message_head: Vec<u8> = self.buffer.drain(..head_end).collect();
+                return Ok(Some(message_head));
+            }
+            searched_up_to = self.buffer.len();
+
+            // Stop early (and with a more specific error) if the request-line alone is already too long
+            let request_line_limit: usize = parser_limits.max_request_line_length + 2; // the request-line plus its CRLF
+            if self.buffer.len() >= request_line_limit && !self.buffer[..request_line_limit].windows(2).any(|window| window == b"\r\n") {
+                super::http::ParserLimits::log_exceeded("max_request_line_length", parser_limits.max_request_line_length as u64);
+                return Err(StreamReadError::RequestLineTooLong)
+            }
+            if self.buffer.len() >= max_header_section_size { return Err(header_section_too_large(parser_limits)) }
+
+            match self.fill_buffer() {
+                Ok(0) if self.buffer.is_empty() => return Ok(None), // Connection closed by the client between messages
+                Ok(0) => return Err(StreamReadError::UnexpectedEof),
+                Ok(_) => {}
+                Err(e) => return Err(StreamReadError::Io(e)),
+            }
+        }
+    }
+
+    // Appends a single read from the stream to the buffer, returning the number of bytes read
+    fn fill_buffer(&mut self) -> std::io::Result<usize> {
+        let mut chunk: [u8; READ_CHUNK_SIZE] = [0; READ_CHUNK_SIZE];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(bytes_read) => {
+                    self.buffer.extend_from_slice(&chunk[..bytes_read]);
+                    return Ok(bytes_read);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+fn header_section_too_large(parser_limits: &super::http::ParserLimits) -> StreamReadError {
+    super::http::ParserLimits::log_exceeded("max_header_section_size", parser_limits.max_header_section_size as u64);
+    StreamReadError::HeaderSectionTooLarge
+}
+
+// Reading from an HttpStreamReader drains any buffered (leftover) bytes before touching the stream again
+impl<R: std::io::Read> std::io::Read for HttpStreamReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.buffer.is_empty() { return self.stream.read(buf) }
+        let n: usize = std::cmp::min(buf.len(), self.buffer.len());
+        buf[..n].copy_from_slice(&self.buffer[..n]);
+        self.buffer.drain(..n);
+        Ok(n)
+    }
+}
+
+// Lets line oriented decoders (e.g., the chunked transfer coding) work directly on top of the buffer
+impl<R: std::io::Read> std::io::BufRead for HttpStreamReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        if self.buffer.is_empty() { self.fill_buffer()?; }
+        Ok(&self.buffer)
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.buffer.drain(..amt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::http::ParserLimits;
+    use std::io::Read;
+
+    fn parser_limits(max_request_line_length: usize, max_header_section_size: usize) -> ParserLimits {
+        ParserLimits { max_request_line_length, max_header_section_size, ..ParserLimits::default() }
+    }
+
+    // A stream that hands out its data a few bytes at a time, like a request split over several TCP segments
+    struct TrickleStream { data: Vec<u8>, position: usize, step: usize }
+
+    impl std::io::Read for TrickleStream {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let end: usize = std::cmp::min(self.data.len(), self.position + std::cmp::min(self.step, buf.len()));
+            let n: usize = end - self.position;
+            buf[..n].copy_from_slice(&self.data[self.position..end]);
+            self.position = end;
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn test_read_message_head_across_segments() {
+        let data: Vec<u8> = b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\nbody bytes".to_vec();
+        let mut reader: HttpStreamReader<TrickleStream> = HttpStreamReader::new(TrickleStream { data, position: 0, step: 3 });
+
+        let head: Vec<u8> = reader.read_message_head(&parser_limits(1024, 1024)).unwrap().unwrap();
+        assert_eq!(head, b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n".to_vec());
+
+        let mut rest: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"body bytes".to_vec()); // leftover bytes come out before the rest of the stream
+    }
+
+    #[test]
+    fn test_read_message_head_limits_and_eof() {
+        let large: Vec<u8> = [b"GET / HTTP/1.1\r\nCookie: ".to_vec(), b"a".repeat(2048), b"\r\n\r\n".to_vec()].concat();
+        let mut reader: HttpStreamReader<&[u8]> = HttpStreamReader::new(&large[..]);
+        assert!(matches!(reader.read_message_head(&parser_limits(1024, 1024)), Err(StreamReadError::HeaderSectionTooLarge)));
+
+        let mut reader: HttpStreamReader<&[u8]> = HttpStreamReader::new(&large[..]);
+        assert!(reader.read_message_head(&parser_limits(1024, 4096)).unwrap().is_some()); // headers over 1 KiB are fine when allowed
+
+        let mut reader: HttpStreamReader<&[u8]> = HttpStreamReader::new(&b""[..]);
+        assert!(reader.read_message_head(&parser_limits(1024, 1024)).unwrap().is_none()); // closed before anything was sent
+
+        let mut reader: HttpStreamReader<&[u8]> = HttpStreamReader::new(&b"GET / HTTP/1.1\r\nHost"[..]);
+        assert!(matches!(reader.read_message_head(&parser_limits(1024, 1024)), Err(StreamReadError::UnexpectedEof)));
+
+        let long_request_line: Vec<u8> = [b"GET /".to_vec(), b"a".repeat(2048), b" HTTP/1.1\r\n\r\n".to_vec()].concat();
+        let mut reader: HttpStreamReader<TrickleStream> = HttpStreamReader::new(TrickleStream { data: long_request_line, position: 0, step: 512 });
+        assert!(matches!(reader.read_message_head(&parser_limits(1024, 4096)), Err(StreamReadError::RequestLineTooLong))); // caught before the rest arrives
+    }
+}
@@ -0,0 +1,191 @@
+// tcp/resolver.rs
+
+// The names (and name suffixes) of backup and editor files, which are never meant to be served even though they sit next to the pages they are copies of
+const BACKUP_FILE_SUFFIXES: [&str; 5] = ["~", ".bak", ".orig", ".swp", ".tmp"];
+
+// The one directory starting with "." that is meant to be public: "/.well-known/" - rfc8615#section-3
+const WELL_KNOWN_DIRECTORY: &str = ".well-known";
+
+// Which symbolic links under the site root may be followed to serve a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    Deny,       // never follow a symbolic link
+    WithinRoot, // follow symbolic links that lead to somewhere under the site root
+    Follow,     // follow every symbolic link, wherever it leads (i.e., the site root isn't a sandbox for whoever can create links in it)
+}
+
+impl std::str::FromStr for SymlinkPolicy {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<SymlinkPolicy, ()> {
+        match value.to_ascii_lowercase().as_str() {
+            "deny" => Ok(SymlinkPolicy::Deny),
+            "within-root" => Ok(SymlinkPolicy::WithinRoot),
+            "follow" => Ok(SymlinkPolicy::Follow),
+            _ => Err(()),
+        }
+    }
+}
+
+// How a request for a blocked path is answered. 404 doesn't even reveal that there is something at the path, 403 tells the client not to bother asking again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockedPathPolicy {
+    Forbidden, // 403
+    NotFound,  // 404
+}
+
+impl std::str::FromStr for BlockedPathPolicy {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<BlockedPathPolicy, ()> {
+        match value {
+            "403" => Ok(BlockedPathPolicy::Forbidden),
+            "404" => Ok(BlockedPathPolicy::NotFound),
+            _ => Err(()),
+        }
+    }
+}
+
+impl BlockedPathPolicy {
+    pub fn status_code(&self) -> super::http::StatusCode {
+        match self {
+            BlockedPathPolicy::Forbidden => super::http::StatusCode::FORBIDDEN,
+            BlockedPathPolicy::NotFound => super::http::StatusCode::NOT_FOUND,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ResolveError {
+    NotFound, // There is nothing at the path
+    Blocked,  // There may be something at the path, but it is outside of the site root, hidden or behind a symbolic link that isn't followed
+}
+
+// Maps the path of a request (percent-decoded, free of dot-segments and starting with "/") to the file it names under the site root.
+// Whatever the request path is, the file returned is under the site root (unless SymlinkPolicy::Follow lets a symbolic link lead elsewhere):
+//   - every segment has to be a plain file name, so the path can't climb out of the root (e.g., "..", "C:" or a "\" on Windows)
+//   - hidden files (names starting with ".", apart from .well-known) and backup files (e.g., "index.html~") are blocked, unless config.serve_hidden_files
+//   - the path is canonicalised and has to still be under the canonical site root, so no symbolic link can lead out of it (or to a hidden file in it) either
+pub fn resolve_path(request_path: &str, config: &super::ServerConfig) -> Result<std::path::PathBuf, ResolveError> {
+    let mut path: std::path::PathBuf = config.site_root.clone();
+    let mut is_symlink: bool = false;
+    for segment in request_path.split('/').filter(|segment| !segment.is_empty()) {
+        if !is_file_name(segment) { return Err(ResolveError::Blocked) }
+        if !config.serve_hidden_files && is_hidden(segment) { return Err(ResolveError::Blocked) }
+        path.push(segment);
+        // Checked segment by segment, since a symbolic link to a directory would otherwise go unnoticed
+        match std::fs::symlink_metadata(&path) {
+            Ok(metadata) => is_symlink = is_symlink || metadata.file_type().is_symlink(),
+            Err(_) => return Err(ResolveError::NotFound),
+        }
+    }
+    if is_symlink && config.symlink_policy == SymlinkPolicy::Deny { return Err(ResolveError::Blocked) }
+
+    let canonical_path: std::path::PathBuf = path.canonicalize().map_err(|_| ResolveError::NotFound)?;
+    let canonical_root: std::path::PathBuf = config.site_root.canonicalize().map_err(|_| ResolveError::NotFound)?;
+    match canonical_path.strip_prefix(&canonical_root) {
+        // A symbolic link with an ordinary name can still lead to a hidden file (e.g., "pub" -> ".git"), so the path it leads to is checked as well
+        Ok(relative_path) if !config.serve_hidden_files && relative_path.components().any(|component| is_hidden(&component.as_os_str().to_string_lossy())) => {
+            println!("WARNING (RESOLVE_PATH): Blocked {}, it leads to the hidden file {}", request_path, canonical_path.display());
+            return Err(ResolveError::Blocked);
+        }
+        Ok(_) => {}
+        Err(_) if config.symlink_policy == SymlinkPolicy::Follow => {}
+        Err(_) => {
+            println!("WARNING (RESOLVE_PATH): Blocked {}, it leads out of the site root to {}", request_path, canonical_path.display());
+            return Err(ResolveError::Blocked);
+        }
+    }
+    Ok(canonical_path)
+}
+
+// Finds the index file of the directory a request path (ending in "/") names, the first of config.index_files that is a file under the site root
+pub fn resolve_index(request_path: &str, config: &super::ServerConfig) -> Result<std::path::PathBuf, ResolveError> {
+    for index_file in &config.index_files {
+        match resolve_path(&format!("{}{}", request_path, index_file), config) {
+            Ok(path) if path.is_file() => return Ok(path),
+            _ => continue,
+        }
+    }
+    Err(ResolveError::NotFound)
+}
+
+// Whether a path segment is a single, ordinary file name (i.e., not "..", not a root or prefix, and without separators)
+fn is_file_name(segment: &str) -> bool {
+    let mut components = std::path::Path::new(segment).components();
+    matches!((components.next(), components.next()), (Some(std::path::Component::Normal(name)), None) if name == segment)
+}
+
+// Whether a file name is that of a hidden file (e.g., ".git", ".env") or of a backup file (e.g., "index.html~", "config.bak")
+fn is_hidden(name: &str) -> bool {
+    (name.starts_with('.') && name != WELL_KNOWN_DIRECTORY) || BACKUP_FILE_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::ServerConfig;
+
+    #[test]
+    fn test_is_hidden() {
+        assert!(is_hidden(".git"));
+        assert!(is_hidden(".env"));
+        assert!(is_hidden("index.html~"));
+        assert!(is_hidden("config.php.bak"));
+        assert!(!is_hidden(".well-known"));
+        assert!(!is_hidden("index.html"));
+        assert!(!is_hidden("v1.2"));
+
+        assert!(is_file_name("index.html"));
+        assert!(!is_file_name(".."));
+        assert!(!is_file_name("."));
+    }
+
+    #[test]
+    fn test_resolve_path() {
+        // <temp>/root/{index.html, .env, styles/style.css}, and <temp>/outside.txt next to the root
+        let temp_dir: std::path::PathBuf = std::env::temp_dir().join(format!("server_test_resolve_path_{}", std::process::id()));
+        let site_root: std::path::PathBuf = temp_dir.join("root");
+        std::fs::create_dir_all(site_root.join("styles")).unwrap();
+        std::fs::write(site_root.join("index.html"), b"").unwrap();
+        std::fs::write(site_root.join(".env"), b"").unwrap();
+        std::fs::write(site_root.join("styles/style.css"), b"").unwrap();
+        std::fs::write(temp_dir.join("outside.txt"), b"").unwrap();
+        let mut config: ServerConfig = ServerConfig { site_root: site_root.clone(), ..ServerConfig::default() };
+        let canonical_root: std::path::PathBuf = site_root.canonicalize().unwrap();
+
+        assert_eq!(resolve_path("/index.html", &config), Ok(canonical_root.join("index.html")));
+        assert_eq!(resolve_path("/styles//style.css", &config), Ok(canonical_root.join("styles/style.css")));
+        assert_eq!(resolve_path("/missing.html", &config), Err(ResolveError::NotFound));
+        assert_eq!(resolve_path("/../outside.txt", &config), Err(ResolveError::Blocked));
+        assert_eq!(resolve_path("/.env", &config), Err(ResolveError::Blocked));
+        config.serve_hidden_files = true;
+        assert_eq!(resolve_path("/.env", &config), Ok(canonical_root.join(".env")));
+
+        // The first index file that exists is used
+        assert_eq!(resolve_path("/", &config), Ok(canonical_root.clone()));
+        assert_eq!(resolve_index("/", &config), Ok(canonical_root.join("index.html")));
+        assert_eq!(resolve_index("/styles/", &config), Err(ResolveError::NotFound));
+        config.index_files = vec![String::from("style.css")];
+        assert_eq!(resolve_index("/styles/", &config), Ok(canonical_root.join("styles/style.css")));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(temp_dir.join("outside.txt"), site_root.join("outside.txt")).unwrap();
+            std::os::unix::fs::symlink(site_root.join("styles"), site_root.join("css")).unwrap();
+            std::fs::create_dir(site_root.join(".git")).unwrap();
+            std::fs::write(site_root.join(".git/config"), b"").unwrap();
+            std::os::unix::fs::symlink(site_root.join(".git"), site_root.join("pub")).unwrap();
+            assert_eq!(resolve_path("/outside.txt", &config), Err(ResolveError::Blocked));
+            assert_eq!(resolve_path("/css/style.css", &config), Ok(canonical_root.join("styles/style.css")));
+            assert_eq!(resolve_path("/pub/config", &config), Ok(canonical_root.join(".git/config"))); // config.serve_hidden_files is still set
+            config.serve_hidden_files = false;
+            assert_eq!(resolve_path("/pub/config", &config), Err(ResolveError::Blocked)); // an in-root symbolic link to a hidden directory
+            config.symlink_policy = SymlinkPolicy::Deny;
+            assert_eq!(resolve_path("/css/style.css", &config), Err(ResolveError::Blocked));
+            config.symlink_policy = SymlinkPolicy::Follow;
+            assert_eq!(resolve_path("/outside.txt", &config), Ok(temp_dir.canonicalize().unwrap().join("outside.txt")));
+        }
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
@@ -12,6 +12,7 @@ type ArcMutexReceiver = std::sync::Arc<std::sync::Mutex<std::sync::mpsc::Receive
 
 // Create a Worker struct that contains a thread handle. The thread handle is a JoinHandle that is used to join the thread.
 struct Worker {
+    #[allow(dead_code)] // never joined (yet), but keeps the handle alive for as long as the Worker is
     thread: std::thread::JoinHandle<()>,
 }
 
@@ -25,11 +26,19 @@ impl Worker {
             // The loop will continue to run as long as the receiver is able to receive jobs
             // The receiver is a mutex-protected channel receiver. The receiver is locked and the recv method is called to receive a job.
             //
-            //                              Mutex      Receiver
-            //                             ___|___        |
-            //                            |       |       |
-            while let Ok(job) = receiver.lock().unwrap().recv() { //handle errors here, no unwrap
-                job();
+            // The lock is released as soon as a job has been received (the guard is dropped at the end of the `let` statement),
+            // so the other workers can pick up jobs while this one runs its job.
+            // A poisoned mutex only means another worker panicked while holding the lock; the receiver itself is still fine.
+            loop {
+                let job: Job = match receiver.lock().unwrap_or_else(std::sync::PoisonError::into_inner).recv() {
+                    Ok(job) => job,
+                    Err(_) => break, // The Pool (i.e., the sender) has been dropped
+                };
+
+                // Last line of defense: a panic in a job (e.g., a bug triggered by one client's request) must not take the worker down with it
+                if std::panic::catch_unwind(std::panic::AssertUnwindSafe(job)).is_err() {
+                    println!("ERROR (WORKER): A job panicked. The worker will carry on with the next job.");
+                }
             }
         });
 
@@ -39,6 +48,7 @@ impl Worker {
 }
 
 pub struct Pool {
+    #[allow(dead_code)] // the Pool owns its Workers even though it never reads them back
     workers: Vec<Worker>,
     sender: std::sync::mpsc::Sender<Job>,
 }